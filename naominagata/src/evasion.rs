@@ -0,0 +1,100 @@
+use oort_api::prelude::*;
+
+/// Bullets predicted to pass closer than this (m) are treated as hits.
+const LETHAL_RADIUS: f64 = 20.0;
+/// Bullets further than this (s) from closest approach are ignored.
+const EVASION_HORIZON: f64 = 2.0;
+/// Acceleration (m/s^2) commanded to dodge a bullet about to arrive.
+const MAX_EVASION_ACCEL: f64 = 60.0;
+
+/// Time (s) until a bullet at `r_rel` moving at `v_rel` (both relative to us)
+/// is closest, and its miss distance then. `None` if it is receding.
+fn closest_approach(r_rel: Vec2, v_rel: Vec2) -> Option<(f64, Vec2)> {
+    let speed_sq = v_rel.dot(v_rel);
+    if speed_sq < 1e-9 {
+        return None;
+    }
+    let t = -r_rel.dot(v_rel) / speed_sq;
+    (t > 0.0).then(|| (t, r_rel + v_rel * t))
+}
+
+/// Evasive acceleration against the most urgent of `threats` (bullet
+/// position, velocity), or `None` if none will pass within the lethal radius
+/// inside the evasion horizon.
+///
+/// The most urgent threat is the one with the earliest closest approach. The
+/// dodge is perpendicular to its relative velocity, away from the side it
+/// would pass on (to the left of its path for a dead-centre shot), and grows
+/// from zero at the horizon to `MAX_EVASION_ACCEL` at impact.
+pub fn evasion_accel(threats: &[(Vec2, Vec2)], my_pos: Vec2, my_vel: Vec2) -> Option<Vec2> {
+    let mut most_urgent: Option<(f64, Vec2, Vec2)> = None;
+    for &(position, velocity) in threats {
+        let v_rel = velocity - my_vel;
+        let Some((t, miss)) = closest_approach(position - my_pos, v_rel) else {
+            continue;
+        };
+        if t > EVASION_HORIZON || miss.length() > LETHAL_RADIUS {
+            continue;
+        }
+        if most_urgent.is_none_or(|(t_best, _, _)| t < t_best) {
+            most_urgent = Some((t, miss, v_rel));
+        }
+    }
+
+    let (t, miss, v_rel) = most_urgent?;
+    let left = v_rel.normalize().rotate(std::f64::consts::FRAC_PI_2);
+    // The bullet passes on the `miss` side of us, so move the other way.
+    let away = if miss.dot(left) > 0.0 { -left } else { left };
+    Some(away * MAX_EVASION_ACCEL * (1.0 - t / EVASION_HORIZON))
+}
+
+/// Blends an attack acceleration with an evasive one: the attack command is
+/// limited to `MAX_EVASION_ACCEL` and scaled down as the evasion grows, so
+/// an imminent hit takes priority over closing on the target.
+pub fn blend_evasion(attack: Vec2, evasion: Vec2) -> Vec2 {
+    let urgency = (evasion.length() / MAX_EVASION_ACCEL).min(1.0);
+    let attack = if attack.length() > MAX_EVASION_ACCEL {
+        attack.normalize() * MAX_EVASION_ACCEL
+    } else {
+        attack
+    };
+    attack * (1.0 - urgency) + evasion
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dodges_head_on_bullet_sideways() {
+        let bullet = (vec2(1000.0, 0.0), vec2(-1000.0, 0.0));
+        let accel = evasion_accel(&[bullet], vec2(0.0, 0.0), vec2(0.0, 0.0)).unwrap();
+        assert!(accel.x.abs() < 1e-9);
+        assert!(accel.y.abs() > 0.0);
+
+        // A bullet about to hit demands a harder dodge than one further out.
+        let close = (vec2(200.0, 0.0), vec2(-1000.0, 0.0));
+        let urgent = evasion_accel(&[bullet, close], vec2(0.0, 0.0), vec2(0.0, 0.0)).unwrap();
+        assert!(urgent.length() > accel.length());
+    }
+
+    #[test]
+    fn dodges_away_from_near_side() {
+        // Passing 10 m above us: move down.
+        let bullet = (vec2(1000.0, 10.0), vec2(-1000.0, 0.0));
+        let accel = evasion_accel(&[bullet], vec2(0.0, 0.0), vec2(0.0, 0.0)).unwrap();
+        assert!(accel.y < 0.0);
+    }
+
+    #[test]
+    fn ignores_bullets_already_missing() {
+        let me = vec2(0.0, 0.0);
+        let receding = (vec2(500.0, 0.0), vec2(1000.0, 0.0));
+        let wide = (vec2(1000.0, 200.0), vec2(-1000.0, 0.0));
+        assert_eq!(evasion_accel(&[receding, wide], me, vec2(0.0, 0.0)), None);
+
+        // Moving with a bullet that would otherwise hit leaves it receding.
+        let chasing = (vec2(-500.0, 0.0), vec2(100.0, 0.0));
+        assert_eq!(evasion_accel(&[chasing], me, vec2(200.0, 0.0)), None);
+    }
+}
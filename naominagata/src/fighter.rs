@@ -1,60 +1,1619 @@
 // Tutorial: Guns
 // Destroy the asteroid.
-use crate::BULLET_SPEED;
+use crate::evasion::{blend_evasion, evasion_accel};
 use crate::pid::Pid;
-use crate::target::Target;
+use crate::target::{FiringSolution, INTERCEPT_TIE_WINDOW, Target};
+use crate::{BULLET_SPEED, MAX_FLIGHT_TIME};
 use oort_api::prelude::*;
+use std::collections::HashMap;
+
+/// A weapon the ship aims and fires.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weapon {
+    /// Index passed to `fire`.
+    pub index: usize,
+    /// Projectile speed (m/s).
+    pub speed: f64,
+    /// Projectile lifetime (s); intercepts later than this are out of reach.
+    /// Every solver is bounded by it, so it may exceed the 10 s default.
+    pub max_flight_time: f64,
+}
+
+/// Per-ship-class tuning for [`Ship`]; `Default` gives the values tuned for a fighter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShipConfig {
+    /// Weapons to solve for each tick; the best valid solution is fired.
+    pub weapons: Vec<Weapon>,
+    /// Heading PID gains.
+    pub heading_kp: f64,
+    pub heading_ki: f64,
+    pub heading_kd: f64,
+    /// Miss distance (m) at the target within which a shot is taken.
+    pub fire_tolerance: f64,
+    /// Gain (1/s^2) from the aim point offset to the commanded acceleration.
+    pub accel_gain: f64,
+    /// Position PD gains, applied per axis, used when holding a standoff.
+    pub position_kp: f64,
+    pub position_kd: f64,
+    /// Largest acceleration (m/s^2) the position controller commands.
+    pub max_accel: f64,
+    /// Gain on the aim angle's angular acceleration fed forward as torque; 1
+    /// matches the aim exactly (torque commands angular acceleration), 0 is pure PID.
+    pub aim_accel_feedforward: f64,
+    /// Time constant (s) of the low-pass on the commanded acceleration; zero disables it.
+    pub accel_time_constant: f64,
+    /// Gun position in the body frame (x forward); bullets spawn here, not at the centroid.
+    pub muzzle_offset: Vec2,
+    /// Below this aim distance (m) the aim angle is held rather than recomputed,
+    /// since the direction of a tiny vector is dominated by noise.
+    pub min_aim_distance: f64,
+    /// Maximum rate (rad/s) at which the aim setpoint may move, so a noisy
+    /// lead doesn't whip the heading around.
+    pub max_aim_rate: f64,
+    /// Fraction of each miss passed to `Ship::record_miss` folded into `aim_bias`.
+    pub miss_bias_gain: f64,
+    /// Heading to hold when there is no firing solution.
+    pub idle_heading: IdleHeading,
+    /// Whether to brake to a stop when there is no firing solution.
+    pub idle_decelerate: bool,
+    /// Prefer whichever valid intercept keeps the target nearest the current
+    /// heading over simply the earliest one.
+    pub keep_target_in_front: bool,
+    /// How much later (s) than the earliest an intercept may be and still be
+    /// preferred under `keep_target_in_front`.
+    pub intercept_tie_window: f64,
+    /// Whether the first two configured guns with the solved weapon's projectile
+    /// speed share an arc and should alternate rather than volley.
+    pub staggered_guns: bool,
+    /// Reload period (ticks) of each of the staggered guns.
+    pub gun_reload_ticks: u32,
+    /// Half-width (rad) of the gun's firing arc about the nose.
+    pub gun_arc: f64,
+    /// Turn rate (rad/s) assumed when estimating slews.
+    pub max_turn_rate: f64,
+    /// Missile launcher weapon index; `None` disables missiles.
+    pub missile_weapon: Option<usize>,
+    /// Missiles are only launched at targets beyond this range (m), where guns are weak.
+    pub missile_min_range: f64,
+    /// Minimum ticks between two missile launches at the target.
+    pub missile_cooldown_ticks: u32,
+    /// Ticks a launched missile stays in flight; guidance is radioed only until
+    /// the last one launched has expired.
+    pub missile_flight_ticks: u32,
+    /// Distance (m) from the arena edge at which wall avoidance starts.
+    pub wall_margin: f64,
+    /// Acceleration (m/s^2) used to steer away from a wall at the edge.
+    pub wall_avoid_accel: f64,
+    /// When set, hold this distance (m) short of the target under
+    /// `position_control` instead of pushing toward the aim point.
+    pub standoff_distance: Option<f64>,
+}
+
+impl Default for ShipConfig {
+    fn default() -> Self {
+        // PID gains tuned empirically for stable heading control.
+        Self {
+            weapons: vec![Weapon {
+                index: 0,
+                speed: BULLET_SPEED,
+                max_flight_time: MAX_FLIGHT_TIME,
+            }],
+            heading_kp: 8.0,
+            heading_ki: 0.0,
+            heading_kd: 5.0,
+            fire_tolerance: FIRE_TOLERANCE,
+            accel_gain: 1000.0,
+            // Overdamped (poles at -0.5 and -2 rad/s) so the approach
+            // settles without overshoot even while saturated.
+            position_kp: 1.0,
+            position_kd: 2.5,
+            max_accel: 60.0,
+            aim_accel_feedforward: 0.0,
+            accel_time_constant: 0.1,
+            muzzle_offset: vec2(0.0, 0.0),
+            min_aim_distance: 20.0,
+            max_aim_rate: std::f64::consts::TAU,
+            miss_bias_gain: 0.2,
+            idle_heading: IdleHeading::LastContact,
+            idle_decelerate: true,
+            keep_target_in_front: false,
+            intercept_tie_window: INTERCEPT_TIE_WINDOW,
+            staggered_guns: false,
+            gun_reload_ticks: 10,
+            gun_arc: 0.0,
+            max_turn_rate: 2.0,
+            missile_weapon: None,
+            missile_min_range: 5000.0,
+            missile_cooldown_ticks: 120,
+            missile_flight_ticks: 600,
+            wall_margin: 1000.0,
+            wall_avoid_accel: 60.0,
+            standoff_distance: None,
+        }
+    }
+}
 
 pub struct Ship {
+    config: ShipConfig,
     pid: Pid,
+    /// Per-axis (x, y) position loops for `position_control`.
+    position_pid: [Pid; 2],
     target: Target,
+    /// Acceleration applied on the previous tick: the smoothed command as
+    /// limited by the hull, not the raw request.
+    accel_command: Vec2,
+    /// Aim angle used on the previous tick.
+    last_aim_angle: Option<f64>,
+    /// Correction added to the aim point to cancel systematic misses, in the
+    /// target's track frame (x along its velocity, y to its left), so a lead
+    /// error learned on one pass still applies after the target turns.
+    aim_bias: Vec2,
+    /// Friendly ships as (position, radius); shots that would pass through one are held.
+    pub friendlies: Vec<(Vec2, f64)>,
+    /// Incoming bullets as (position, velocity), dodged in favour of attacking.
+    pub threats: Vec<(Vec2, Vec2)>,
+    /// Hull radius (m) of the target; the ship aims at the near edge rather than the centroid.
+    pub target_radius: f64,
+    /// Tick each staggered gun last fired on, by weapon index.
+    last_fired: HashMap<usize, u32>,
+    /// Magazine size, set by `load_ammo`; `None` for unlimited ammunition.
+    ammo_capacity: Option<u32>,
+    /// Rounds left when `ammo_capacity` is set.
+    ammo_remaining: u32,
+    /// Tick of the last missile launch.
+    last_missile_launch: Option<u32>,
+    /// Whether to draw the target, aim point, lead pip and aim line, and log via `debug!`.
+    debug: bool,
+}
+
+impl Default for Ship {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the ship faces while it has nothing to shoot at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdleHeading {
+    /// Face the centre of the arena.
+    ArenaCenter,
+    /// Face the last known target position.
+    LastContact,
+    /// Hold a fixed world-frame heading (rad).
+    Fixed(f64),
+}
+
+/// What the ship reads from the game at the start of a tick.
+#[derive(Debug, Clone, Copy)]
+struct Sensors {
+    position: Vec2,
+    velocity: Vec2,
+    heading: f64,
+    tick: u32,
+    target_position: Vec2,
+    target_velocity: Vec2,
+    world_size: f64,
+    accel_limits: AccelLimits,
+}
+
+/// Largest acceleration (m/s^2) the hull delivers along and across its nose;
+/// the game clamps each body-frame component of a command to these.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AccelLimits {
+    forward: f64,
+    backward: f64,
+    lateral: f64,
+}
+
+impl Sensors {
+    fn read() -> Self {
+        Self {
+            position: position(),
+            velocity: velocity(),
+            heading: heading(),
+            tick: current_tick(),
+            target_position: target(),
+            target_velocity: target_velocity(),
+            world_size: world_size(),
+            accel_limits: AccelLimits {
+                forward: max_forward_acceleration(),
+                backward: max_backward_acceleration(),
+                lateral: max_lateral_acceleration(),
+            },
+        }
+    }
+}
+
+/// What the ship does on a tick: torque, acceleration and the weapons to fire.
+#[derive(Debug, Clone, PartialEq)]
+struct Commands {
+    torque: f64,
+    accel: Vec2,
+    fire: Vec<usize>,
+}
+
+/// Default miss distance (m) at the target within which a shot is taken.
+const FIRE_TOLERANCE: f64 = 10.0;
+
+/// Minimum hit probability to fire with a full magazine; this matches the
+/// plain `miss < fire_tolerance` gate, since `hit_probability` is `e^-1` there.
+const FULL_AMMO_MIN_HIT_PROB: f64 = 1.0 / std::f64::consts::E;
+/// Minimum hit probability to fire with an empty magazine.
+const LOW_AMMO_MIN_HIT_PROB: f64 = 0.95;
+
+/// Gain (1/s) of the idle braking command, `accel = -gain * velocity`.
+const IDLE_BRAKE_GAIN: f64 = 2.0;
+
+/// Below this target speed (m/s) its track has no meaningful direction, and
+/// the aim bias is kept in the line-of-sight frame instead.
+const MIN_TRACK_SPEED: f64 = 1.0;
+
+/// Position and velocity after `t` seconds of constant acceleration `accel`.
+fn extrapolate(position: Vec2, velocity: Vec2, accel: Vec2, t: f64) -> (Vec2, Vec2) {
+    (
+        position + velocity * t + 0.5 * accel * t * t,
+        velocity + accel * t,
+    )
+}
+
+/// The acceleration a hull facing `heading` actually delivers for a command of
+/// `accel`: each body-frame component clamped to `limits`.
+fn hull_limited(accel: Vec2, heading: f64, limits: AccelLimits) -> Vec2 {
+    let body = accel.rotate(-heading);
+    vec2(
+        body.x.clamp(-limits.backward, limits.forward),
+        body.y.clamp(-limits.lateral, limits.lateral),
+    )
+    .rotate(heading)
+}
+
+/// Whether a bullet leaving `origin` at `bullet_velocity` (muzzle velocity
+/// plus the ship's own) passes within a friendly's radius in `flight_time`.
+fn line_of_fire_blocked(
+    origin: Vec2,
+    bullet_velocity: Vec2,
+    flight_time: f64,
+    friendlies: &[(Vec2, f64)],
+) -> bool {
+    let dir = bullet_velocity.normalize();
+    let max_range = bullet_velocity.length() * flight_time;
+    friendlies.iter().any(|&(friendly, radius)| {
+        let rel = friendly - origin;
+        let along = rel.dot(dir).clamp(0.0, max_range);
+        (rel - dir * along).length() < radius
+    })
+}
+
+/// Which of two identical guns to fire on `tick` so that they alternate half
+/// a reload period apart, doubling the sustained rate of aimed fire.
+///
+/// A gun fires when it has reloaded and the other gun's last shot was at least
+/// half a period ago; gun 0 wins if both would fire on the same tick.
+fn staggered_fire(tick: u32, reload_ticks: u32, last_fired: [Option<u32>; 2]) -> [bool; 2] {
+    let since = |gun: usize| last_fired[gun].map(|t| tick - t);
+    let ready = |gun: usize| since(gun).is_none_or(|dt| dt >= reload_ticks);
+    let offset_from = |other: usize| since(other).is_none_or(|dt| dt >= reload_ticks / 2);
+
+    let fire_0 = ready(0) && offset_from(1);
+    let fire_1 = !fire_0 && ready(1) && offset_from(0);
+    [fire_0, fire_1]
+}
+
+/// Point `standoff` metres from `target` on the ship's side of it. When the
+/// ship sits on the target there is no such side, so back off behind `heading`.
+fn standoff_point(position: Vec2, heading: f64, target: Vec2, standoff: f64) -> Vec2 {
+    let offset = position - target;
+    let to_ship = if offset.length() > 1e-6 {
+        offset.normalize()
+    } else {
+        -vec2(heading.cos(), heading.sin())
+    };
+    target + to_ship * standoff
+}
+
+/// Whether a round from `weapon`, fired from `muzzle` along `aim_heading` by a
+/// ship moving at `ship_velocity`, passes through one of `friendlies` before it
+/// expires. Rounds inherit the ship's velocity, so the path is traced along the sum.
+fn weapon_line_blocked(
+    weapon: Weapon,
+    muzzle: Vec2,
+    ship_velocity: Vec2,
+    aim_heading: f64,
+    friendlies: &[(Vec2, f64)],
+) -> bool {
+    let bullet_velocity = vec2(aim_heading.cos(), aim_heading.sin()) * weapon.speed + ship_velocity;
+    line_of_fire_blocked(muzzle, bullet_velocity, weapon.max_flight_time, friendlies)
+}
+
+/// The first two of `weapons` whose projectiles fly at the solved weapon's
+/// speed, so either can take the shot; `None` if fewer than two do.
+fn staggered_pair(weapons: &[Weapon], solved: Weapon) -> Option<[Weapon; 2]> {
+    let mut matching = weapons.iter().filter(|w| w.speed == solved.speed).copied();
+    Some([matching.next()?, matching.next()?])
+}
+
+/// Time (s) to slew from `heading` until `bearing` lies within `arc` of the
+/// nose at `max_turn_rate`; `None` if it is out of arc and the ship can't turn.
+fn slew_time(heading: f64, bearing: f64, arc: f64, max_turn_rate: f64) -> Option<f64> {
+    let gap = (angle_diff(heading, bearing).abs() - arc).max(0.0);
+    if gap <= 0.0 {
+        Some(0.0)
+    } else if max_turn_rate > 0.0 {
+        Some(gap / max_turn_rate)
+    } else {
+        None
+    }
+}
+
+/// Rough hit probability for a shot expected to miss the target centre by
+/// `miss` metres: `exp(-(miss / tolerance)^2)`.
+fn hit_probability(miss: f64, tolerance: f64) -> f64 {
+    (-(miss / tolerance).powi(2)).exp()
+}
+
+/// Minimum hit probability needed to fire with `remaining` of `capacity`
+/// rounds left. It rises quadratically from the full-magazine gate toward
+/// near-certainty as ammunition runs out, so the last rounds are saved for
+/// good shots.
+pub fn min_hit_prob_for_ammo(remaining: u32, capacity: u32) -> f64 {
+    let fraction = if capacity == 0 {
+        0.0
+    } else {
+        (remaining as f64 / capacity as f64).min(1.0)
+    };
+    let depletion = 1.0 - fraction;
+    FULL_AMMO_MIN_HIT_PROB
+        + (LOW_AMMO_MIN_HIT_PROB - FULL_AMMO_MIN_HIT_PROB) * depletion * depletion
+}
+
+/// Blends `desired` with a push away from the walls of a square arena of side
+/// `world_size` centred on the origin.
+///
+/// Each wall closer than `margin` contributes its inward normal weighted by how
+/// far into the margin the ship is (0 at the margin, 1 at the wall). With total
+/// weight `w`, the result is `(1 - w)` of `desired` (limited to `max_accel`)
+/// plus `w` of a `max_accel` push away from the walls; outside the margins
+/// `desired` is returned unchanged.
+fn wall_avoidance(
+    desired: Vec2,
+    position: Vec2,
+    world_size: f64,
+    margin: f64,
+    max_accel: f64,
+) -> Vec2 {
+    let half = 0.5 * world_size;
+    let closeness = |gap: f64| ((margin - gap) / margin).clamp(0.0, 1.0);
+    let repulsion = vec2(
+        closeness(half + position.x) - closeness(half - position.x),
+        closeness(half + position.y) - closeness(half - position.y),
+    );
+    let w = repulsion.length().min(1.0);
+    if w <= 0.0 {
+        return desired;
+    }
+    let desired = if desired.length() > max_accel {
+        desired.normalize() * max_accel
+    } else {
+        desired
+    };
+    desired * (1.0 - w) + repulsion.normalize() * max_accel * w
+}
+
+/// Packs a target into the radio message sent to our missiles each tick:
+///
+/// | index | contents                         |
+/// |-------|----------------------------------|
+/// | 0, 1  | target position `x`, `y` (m)     |
+/// | 2, 3  | target velocity `x`, `y` (m/s)   |
+///
+/// All values are world-frame and raw: the ship's own intercept point is solved
+/// for its guns, not the missile, so the missile solves its own intercept from
+/// this state. A message holds four values, so acceleration is left out; the
+/// missile can estimate it by differencing velocity across messages.
+pub fn missile_message(target_position: Vec2, target_velocity: Vec2) -> [f64; 4] {
+    [
+        target_position.x,
+        target_position.y,
+        target_velocity.x,
+        target_velocity.y,
+    ]
+}
+
+/// Rate (rad/s) at which the bearing to a point at `r_rel` moving at `v_rel`
+/// (both relative to us) is turning; zero for a point on top of us.
+fn aim_rate(r_rel: Vec2, v_rel: Vec2) -> f64 {
+    let range_sq = r_rel.dot(r_rel);
+    if range_sq < 1e-9 {
+        return 0.0;
+    }
+    (r_rel.x * v_rel.y - r_rel.y * v_rel.x) / range_sq
+}
+
+/// Angular acceleration (rad/s^2) of the bearing to a point at `r_rel` moving
+/// at `v_rel` and accelerating at `a_rel` (all relative to us), from
+/// differentiating `aim_rate`; zero for a point on top of us.
+fn aim_accel(r_rel: Vec2, v_rel: Vec2, a_rel: Vec2) -> f64 {
+    let range_sq = r_rel.dot(r_rel);
+    if range_sq < 1e-9 {
+        return 0.0;
+    }
+    let rate = aim_rate(r_rel, v_rel);
+    (r_rel.x * a_rel.y - r_rel.y * a_rel.x - 2.0 * rate * r_rel.dot(v_rel)) / range_sq
+}
+
+/// World-frame muzzle position for a ship at `position` facing `heading`.
+fn muzzle_position(position: Vec2, heading: f64, muzzle_offset: Vec2) -> Vec2 {
+    position + muzzle_offset.rotate(heading)
 }
 
 impl Ship {
     pub fn new() -> Ship {
-        // PID gains tuned empirically for stable heading control.
-        let pid = Pid::new(8.0, 0.0, 5.0);
+        Self::with_config(ShipConfig::default())
+    }
+
+    /// # Panics
+    /// Panics if a weapon's `max_flight_time` is not finite and positive.
+    pub fn with_config(config: ShipConfig) -> Ship {
+        for weapon in &config.weapons {
+            let lifetime = weapon.max_flight_time;
+            assert!(
+                lifetime.is_finite() && lifetime > 0.0,
+                "weapon {} max_flight_time ({lifetime}) must be finite and > 0",
+                weapon.index
+            );
+        }
+        let pid = Pid::new(config.heading_kp, config.heading_ki, config.heading_kd);
+        let position_pid = Pid::new(config.position_kp, 0.0, config.position_kd);
         let target = Target::new(vec2(0.0, 0.0), vec2(0.0, 0.0), vec2(0.0, 0.0));
-        Ship { pid, target }
+        Ship {
+            config,
+            pid,
+            position_pid: [position_pid.clone(), position_pid],
+            target,
+            accel_command: vec2(0.0, 0.0),
+            last_aim_angle: None,
+            aim_bias: vec2(0.0, 0.0),
+            friendlies: Vec::new(),
+            threats: Vec::new(),
+            target_radius: 0.0,
+            last_fired: HashMap::new(),
+            ammo_capacity: None,
+            ammo_remaining: 0,
+            last_missile_launch: None,
+            debug: true,
+        }
+    }
+
+    /// The tuning this ship was built with.
+    pub fn config(&self) -> &ShipConfig {
+        &self.config
+    }
+
+    /// Turns debug drawing and logging on or off; when off, no draw calls are made.
+    pub fn set_debug(&mut self, enabled: bool) {
+        self.debug = enabled;
+    }
+
+    /// Fills the magazine to `capacity` rounds, making ammunition finite.
+    pub fn load_ammo(&mut self, capacity: u32) {
+        self.ammo_capacity = Some(capacity);
+        self.ammo_remaining = capacity;
+    }
+
+    /// Whether to fire a shot expected to miss the target centre by `miss` metres.
+    fn fire_allowed(&self, miss: f64) -> bool {
+        let tolerance = self.config.fire_tolerance;
+        miss < tolerance && self.ammo_allows(hit_probability(miss, tolerance))
+    }
+
+    /// Whether a shot with hit probability `p_hit` is worth a round.
+    fn ammo_allows(&self, p_hit: f64) -> bool {
+        match self.ammo_capacity {
+            Some(capacity) => {
+                self.ammo_remaining > 0
+                    && p_hit >= min_hit_prob_for_ammo(self.ammo_remaining, capacity)
+            }
+            None => true,
+        }
+    }
+
+    /// Queues weapon `index` onto `fire`, counting the round against the
+    /// magazine. Only call this for a reloaded gun: the game ignores `fire`
+    /// while a gun reloads, so those calls must not cost a round.
+    fn fire_round(&mut self, index: usize, fire: &mut Vec<usize>) {
+        fire.push(index);
+        self.ammo_remaining = self.ammo_remaining.saturating_sub(1);
+    }
+
+    /// Whether a missile may be launched on `tick` at a target `range` metres
+    /// away: it must be beyond gun range and the launch cooldown must have run out.
+    fn missile_launch_ready(&self, range: f64, tick: u32) -> bool {
+        self.config.missile_weapon.is_some()
+            && range > self.config.missile_min_range
+            && self
+                .last_missile_launch
+                .is_none_or(|last| tick.saturating_sub(last) >= self.config.missile_cooldown_ticks)
+    }
+
+    /// Whether the last missile launched is still in flight on `tick`.
+    fn missile_in_flight(&self, tick: u32) -> bool {
+        self.last_missile_launch
+            .is_some_and(|last| tick.saturating_sub(last) < self.config.missile_flight_ticks)
+    }
+
+    /// Launches a missile at the target when it is beyond gun range and off
+    /// cooldown, and, while a missile is in flight, radios the target's state
+    /// every tick (see `missile_message`).
+    pub fn maybe_launch_missile(&mut self) {
+        let Some(missile) = self.config.missile_weapon else {
+            return;
+        };
+        let tick = current_tick();
+        if self.missile_launch_ready((self.target.position - position()).length(), tick) {
+            fire(missile);
+            self.last_missile_launch = Some(tick);
+        }
+        if self.missile_in_flight(tick) {
+            send(missile_message(self.target.position, self.target.velocity));
+        }
+    }
+
+    /// Steers `desired_accel` away from the arena walls when within
+    /// `wall_margin` of them; see `wall_avoidance`.
+    pub fn avoid_boundaries(&self, desired_accel: Vec2) -> Vec2 {
+        self.avoid_walls(desired_accel, position(), world_size())
+    }
+
+    fn avoid_walls(&self, desired_accel: Vec2, position: Vec2, world_size: f64) -> Vec2 {
+        wall_avoidance(
+            desired_accel,
+            position,
+            world_size,
+            self.config.wall_margin,
+            self.config.wall_avoid_accel,
+        )
+    }
+
+    /// How long until turning at `max_turn_rate` brings `track` into the gun
+    /// arc: its intercept point if solved, else its current position.
+    pub fn time_until_in_arc(&self, track: &Target) -> Option<f64> {
+        let aim = track.intercept_point.unwrap_or(track.position);
+        slew_time(
+            heading(),
+            (aim - position()).angle(),
+            self.config.gun_arc,
+            self.config.max_turn_rate,
+        )
+    }
+
+    /// Whether firing `weapon` along `aim_heading` now risks hitting one of
+    /// `friendlies` (position, radius) before its round expires.
+    pub fn friendly_fire_risk(
+        &self,
+        weapon: Weapon,
+        aim_heading: f64,
+        friendlies: &[(Vec2, f64)],
+    ) -> bool {
+        let muzzle = muzzle_position(position(), heading(), self.config.muzzle_offset);
+        weapon_line_blocked(weapon, muzzle, velocity(), aim_heading, friendlies)
+    }
+
+    /// Solves for every configured weapon and returns the best-scoring one with
+    /// its solution, leaving `target` solved for that weapon.
+    fn best_weapon_solution(
+        &mut self,
+        observer: Vec2,
+        observer_velocity: Vec2,
+    ) -> Option<(Weapon, FiringSolution)> {
+        // Keep the winner's solved state rather than solving for it again.
+        let mut best: Option<(Weapon, FiringSolution, Target)> = None;
+        for &weapon in &self.config.weapons {
+            self.target.max_flight_time = weapon.max_flight_time;
+            self.target
+                .update_firing_solution_from(observer, observer_velocity, weapon.speed);
+            let Some(solution) = self.target.firing_solution() else {
+                continue;
+            };
+            if best
+                .as_ref()
+                .is_none_or(|(_, b, _)| solution.quality_score() > b.quality_score())
+            {
+                best = Some((weapon, solution, self.target.clone()));
+            }
+        }
+        let (weapon, solution, target) = best?;
+        self.target = target;
+        Some((weapon, solution))
+    }
+
+    /// Torque and desired acceleration while there is no firing solution:
+    /// turn toward the configured idle heading and, if enabled, brake.
+    fn idle_command(
+        &mut self,
+        position: Vec2,
+        velocity: Vec2,
+        heading: f64,
+        dt: f64,
+    ) -> (f64, Vec2) {
+        let desired_heading = match self.config.idle_heading {
+            IdleHeading::ArenaCenter => (vec2(0.0, 0.0) - position).angle(),
+            IdleHeading::LastContact => (self.target.position - position).angle(),
+            IdleHeading::Fixed(h) => h,
+        };
+        let control = self.pid.update(angle_diff(heading, desired_heading), dt);
+        // The position loops and aim setpoint don't run while idle; drop their
+        // memory so the first tick after the gap doesn't act on stale state.
+        for pid in &mut self.position_pid {
+            pid.reset();
+        }
+        self.last_aim_angle = None;
+
+        let accel = if self.config.idle_decelerate {
+            velocity * -IDLE_BRAKE_GAIN
+        } else {
+            vec2(0.0, 0.0)
+        };
+        (control, accel)
+    }
+
+    /// Records where a bullet actually passed relative to the target (bullet
+    /// position minus target position at closest approach) and nudges the aim
+    /// bias against it.
+    ///
+    /// The observed miss already includes the current bias, so this integrates
+    /// the residual: a consistent miss drives the bias until the residual is zero.
+    /// `miss` is world-frame; it is stored relative to the target's track.
+    ///
+    /// `Ship` doesn't track its own bullets, so nothing calls this internally:
+    /// the caller must feed it observed misses (e.g. from radar returns on
+    /// bullets), otherwise the bias stays at zero.
+    pub fn record_miss(&mut self, miss: Vec2) {
+        let miss_on_track = miss.rotate(-self.bias_frame());
+        self.aim_bias -= miss_on_track * self.config.miss_bias_gain;
+    }
+
+    /// Direction (rad) of the frame `aim_bias` is kept in: the target's track,
+    /// or the line of sight from the last solve while it is nearly stationary.
+    fn bias_frame(&self) -> f64 {
+        if self.target.velocity.length() > MIN_TRACK_SPEED {
+            self.target.velocity.angle()
+        } else {
+            self.target
+                .firing_solution()
+                .map_or(0.0, |s| (self.target.position - s.observer).angle())
+        }
+    }
+
+    /// The aim bias rotated into the world frame for the target's current track.
+    fn world_aim_bias(&self) -> Vec2 {
+        self.aim_bias.rotate(self.bias_frame())
+    }
+
+    /// Angle of `aim_point_rel`, holding the previous angle while the vector is
+    /// too short for its direction to be meaningful (e.g. target passing overhead),
+    /// and otherwise slewing toward it no faster than `max_aim_rate`.
+    fn aim_angle(&mut self, aim_point_rel: Vec2, dt: f64) -> f64 {
+        let angle = match self.last_aim_angle {
+            Some(prev) if aim_point_rel.length() < self.config.min_aim_distance => prev,
+            Some(prev) => {
+                let max_step = self.config.max_aim_rate * dt;
+                prev + angle_diff(prev, aim_point_rel.angle()).clamp(-max_step, max_step)
+            }
+            None => aim_point_rel.angle(),
+        };
+        self.last_aim_angle = Some(angle);
+        angle
+    }
+
+    /// Where this ship will be in `t` seconds if it holds the acceleration it is applying.
+    pub fn predicted_self_position(&self, t: f64) -> Vec2 {
+        extrapolate(position(), velocity(), self.accel_command, t).0
+    }
+
+    /// This ship's velocity in `t` seconds if it holds the acceleration it is applying.
+    pub fn predicted_self_velocity(&self, t: f64) -> Vec2 {
+        extrapolate(position(), velocity(), self.accel_command, t).1
+    }
+
+    /// Acceleration command that brings the ship to `desired_pos` and holds it there.
+    pub fn position_control(&mut self, desired_pos: Vec2) -> Vec2 {
+        self.position_command(position(), desired_pos, TICK_LENGTH)
+    }
+
+    /// Runs the x and y position loops on the error from `position` to
+    /// `desired_pos`, limiting the combined command to `max_accel`. The
+    /// derivative term damps the approach so the ship settles instead of orbiting.
+    fn position_command(&mut self, position: Vec2, desired_pos: Vec2, dt: f64) -> Vec2 {
+        let error = desired_pos - position;
+        let accel = vec2(
+            self.position_pid[0].update(error.x, dt),
+            self.position_pid[1].update(error.y, dt),
+        );
+        if accel.length() > self.config.max_accel {
+            accel.normalize() * self.config.max_accel
+        } else {
+            accel
+        }
+    }
+
+    /// Torque to turn through `heading_error` while tracking an aim angle that
+    /// accelerates at `aim_accel`.
+    ///
+    /// The PID alone only reacts once the aim has moved away, so it lags a
+    /// target whose bearing rate is changing. The feed-forward term supplies
+    /// `aim_accel_feedforward` of the aim's angular acceleration directly,
+    /// leaving the PID to correct the residual. It has no `dt` in it, so the
+    /// response is the same whatever the tick length.
+    fn heading_torque(&mut self, heading_error: f64, aim_accel: f64, dt: f64) -> f64 {
+        self.pid.update(heading_error, dt) + self.config.aim_accel_feedforward * aim_accel
+    }
+
+    /// Passes the desired acceleration through a first-order low-pass so the
+    /// command ramps rather than jumping as the aim point jitters.
+    ///
+    /// The filter is discretized exactly, so a given time constant gives the
+    /// same response in seconds whatever the tick length.
+    fn smooth_accel(&mut self, desired: Vec2, dt: f64) -> Vec2 {
+        let alpha = 1.0 - (-dt / self.config.accel_time_constant).exp();
+        self.accel_command += (desired - self.accel_command) * alpha;
+        self.accel_command
+    }
+
+    /// Smooths `desired` and limits it to what a hull facing `heading` can
+    /// deliver, storing the result as the applied acceleration. Without the
+    /// limit a distant aim point would be remembered as a huge acceleration.
+    fn applied_accel(&mut self, desired: Vec2, heading: f64, limits: AccelLimits, dt: f64) -> Vec2 {
+        let accel = hull_limited(self.smooth_accel(desired, dt), heading, limits);
+        self.accel_command = accel;
+        accel
     }
 
     pub fn tick(&mut self) {
+        let commands = self.step(Sensors::read(), TICK_LENGTH, reload_ticks);
+        self.maybe_launch_missile();
+        torque(commands.torque);
+        accelerate(commands.accel);
+        for index in commands.fire {
+            fire(index);
+        }
+    }
+
+    /// One pass of the control loop on `sensors`, `dt` seconds after the last;
+    /// `reload_ticks(index)` is how many ticks weapon `index` has left to reload.
+    fn step(&mut self, sensors: Sensors, dt: f64, reload_ticks: impl Fn(usize) -> u32) -> Commands {
+        let Sensors {
+            position,
+            velocity,
+            heading,
+            ..
+        } = sensors;
         self.target
-            .update_state(target(), target_velocity(), vec2(0.0, 0.0));
-        self.target.update_firing_solution();
+            .update_measured(sensors.target_position, sensors.target_velocity, dt);
+        let muzzle = muzzle_position(position, heading, self.config.muzzle_offset);
+        self.target.preferred_heading = self.config.keep_target_in_front.then_some(heading);
+        self.target.tie_window = self.config.intercept_tie_window;
 
-        draw_diamond(target(), 50.0, 0xff0000);
+        if self.debug {
+            draw_diamond(sensors.target_position, 50.0, 0xff0000);
+        }
 
         // Compute firing solution in the ship-centred frame.
-        if self.target.time_to_intercept.is_none() {
-            // No firing solution
-            return;
+        let best = self.best_weapon_solution(muzzle, velocity);
+        let Some((weapon, solution)) = best else {
+            // No firing solution; hold the idle heading rather than drift
+            let (control, accel) = self.idle_command(position, velocity, heading, dt);
+            let accel = self.avoid_walls(accel, position, sensors.world_size);
+            return Commands {
+                torque: control,
+                accel: self.applied_accel(accel, heading, sensors.accel_limits, dt),
+                fire: Vec::new(),
+            };
+        };
+        let t = solution.time_to_intercept;
+        let aim_point_rel =
+            solution.hull_aim_point(self.target_radius) + self.world_aim_bias() - muzzle;
+        let bullet_speed = weapon.speed;
+        let shot_distance = bullet_speed * t;
+
+        if self.debug {
+            debug!("t: {}", t);
+            draw_diamond(muzzle + aim_point_rel, 10.0, 0x00ff00);
+            if let Some(pip) = self.target.lead_pip() {
+                draw_polygon(pip, 5.0, 12, 0.0, 0xffff00);
+            }
+            draw_line(
+                position,
+                position + vec2(heading.cos(), heading.sin()) * bullet_speed * t,
+                0x00ff00,
+            );
+        }
+
+        let aim_angle = self.aim_angle(aim_point_rel, dt);
+
+        let heading_rel_error = angle_diff(heading, aim_angle);
+
+        // --- PID heading control ---
+        // Our own acceleration is last tick's hull-limited command, which the
+        // hull is still applying; the raw request would swamp the feed-forward.
+        let aim_accel = aim_accel(
+            aim_point_rel,
+            solution.target_velocity - velocity,
+            self.target.acceleration - self.accel_command,
+        );
+        let control = self.heading_torque(heading_rel_error, aim_accel, dt);
+
+        // Fire, but only guns that have reloaded: the game ignores the rest.
+        let mut fire = Vec::new();
+        // Judge the shot against the true aim, not the slewed setpoint: lined
+        // up with a setpoint that is still catching up, rounds miss by the lag.
+        let miss = angle_diff(heading, aim_point_rel.angle()).abs() * shot_distance;
+        if self.fire_allowed(miss) {
+            // Each gun must have reloaded and have a clear line for its own reach.
+            let can_fire = |gun: Weapon, friendlies: &[(Vec2, f64)]| {
+                reload_ticks(gun.index) == 0
+                    && !weapon_line_blocked(gun, muzzle, velocity, heading, friendlies)
+            };
+            let pair =
+                staggered_pair(&self.config.weapons, weapon).filter(|_| self.config.staggered_guns);
+            if let Some(guns) = pair {
+                let tick = sensors.tick;
+                let last_fired = guns.map(|gun| self.last_fired.get(&gun.index).copied());
+                let shots = staggered_fire(tick, self.config.gun_reload_ticks, last_fired);
+                for (gun, shoot) in guns.into_iter().zip(shots) {
+                    if shoot && can_fire(gun, &self.friendlies) {
+                        self.fire_round(gun.index, &mut fire);
+                        self.last_fired.insert(gun.index, tick);
+                    }
+                }
+            } else if can_fire(weapon, &self.friendlies) {
+                self.fire_round(weapon.index, &mut fire);
+            }
+        }
+
+        let mut accel = match self.config.standoff_distance {
+            Some(standoff) => {
+                let hold = standoff_point(position, heading, self.target.position, standoff);
+                self.position_command(position, hold, dt)
+            }
+            None => self.config.accel_gain * aim_point_rel,
+        };
+        if let Some(evasion) = evasion_accel(&self.threats, position, velocity) {
+            accel = blend_evasion(accel, evasion);
+        }
+        let accel = self.avoid_walls(accel, position, sensors.world_size);
+        Commands {
+            torque: control,
+            accel: self.applied_accel(accel, heading, sensors.accel_limits, dt),
+            fire,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_in_desired_accel_is_ramped() {
+        let mut ship = Ship::new();
+        let step = vec2(100.0, 0.0);
+
+        let first = ship.smooth_accel(step, TICK_LENGTH);
+        assert!(first.x > 0.0 && first.x < step.x);
+
+        let mut prev = first.x;
+        for _ in 0..10 {
+            let next = ship.smooth_accel(step, TICK_LENGTH).x;
+            assert!(next > prev && next < step.x);
+            prev = next;
+        }
+    }
+
+    #[test]
+    fn muzzle_offset_shifts_close_intercept() {
+        let ship_position = vec2(0.0, 0.0);
+        let muzzle = muzzle_position(ship_position, 0.0, vec2(0.0, 10.0));
+        assert!((muzzle - vec2(0.0, 10.0)).length() < 1e-12);
+
+        let mut from_centroid = Target::new(vec2(100.0, 0.0), vec2(0.0, 0.0), vec2(0.0, 0.0));
+        let mut from_muzzle = Target::new(vec2(100.0, 0.0), vec2(0.0, 0.0), vec2(0.0, 0.0));
+        from_centroid.update_firing_solution_from(ship_position, vec2(0.0, 0.0), BULLET_SPEED);
+        from_muzzle.update_firing_solution_from(muzzle, vec2(0.0, 0.0), BULLET_SPEED);
+
+        let centroid_aim = (from_centroid.intercept_point.unwrap() - ship_position).angle();
+        let muzzle_aim = (from_muzzle.intercept_point.unwrap() - muzzle).angle();
+        assert!(centroid_aim.abs() < 1e-9);
+        assert!(angle_diff(0.0, muzzle_aim) < -0.05);
+        assert!(from_muzzle.time_to_intercept.unwrap() > from_centroid.time_to_intercept.unwrap());
+    }
+
+    #[test]
+    fn aim_angle_is_held_when_target_passes_overhead() {
+        let mut ship = Ship::new();
+        let jumps = |angles: &[f64]| {
+            angles
+                .windows(2)
+                .filter(|w| angle_diff(w[0], w[1]).abs() > 0.5)
+                .count()
+        };
+
+        // Target sweeps from ahead to behind with sub-metre lateral jitter.
+        let aim_vectors: Vec<Vec2> = (0..100)
+            .map(|k| {
+                let jitter = if k % 2 == 0 { 0.5 } else { -0.5 };
+                vec2(50.0 - k as f64, jitter)
+            })
+            .collect();
+        let raw: Vec<f64> = aim_vectors.iter().map(|v| v.angle()).collect();
+        let held: Vec<f64> = aim_vectors
+            .iter()
+            .map(|&v| ship.aim_angle(v, TICK_LENGTH))
+            .collect();
+
+        assert!(jumps(&raw) > 3);
+        assert!(jumps(&held) <= 1);
+    }
+
+    #[test]
+    fn consistent_miss_converges_aim_bias() {
+        let mut ship = Ship::new();
+        ship.target = Target::new(vec2(1000.0, 0.0), vec2(100.0, 0.0), vec2(0.0, 0.0));
+        // 5 m behind the target along its track, whichever way it is moving.
+        let systematic_miss = |ship: &Ship| ship.target.velocity.normalize() * -5.0;
+
+        for _ in 0..50 {
+            // Shifting the aim point shifts where the bullet passes by the same amount.
+            let observed = systematic_miss(&ship) + ship.world_aim_bias();
+            ship.record_miss(observed);
+        }
+
+        assert!((ship.aim_bias - vec2(5.0, 0.0)).length() < 1e-3);
+        assert!((systematic_miss(&ship) + ship.world_aim_bias()).length() < 1e-3);
+
+        // After the target turns around, the learned correction still leads it.
+        ship.target.velocity = vec2(-100.0, 0.0);
+        assert!((ship.world_aim_bias() - vec2(-5.0, 0.0)).length() < 1e-3);
+        assert!((systematic_miss(&ship) + ship.world_aim_bias()).length() < 1e-3);
+
+        // And after a quarter turn.
+        ship.target.velocity = vec2(0.0, 100.0);
+        assert!((systematic_miss(&ship) + ship.world_aim_bias()).length() < 1e-3);
+    }
+
+    #[test]
+    fn zero_accel_prediction_is_linear_extrapolation() {
+        let r = vec2(100.0, -50.0);
+        let v = vec2(30.0, 40.0);
+        for t in [0.0, 0.5, 2.0] {
+            let (p, u) = extrapolate(r, v, vec2(0.0, 0.0), t);
+            assert!((p - (r + v * t)).length() < 1e-12);
+            assert!((u - v).length() < 1e-12);
+        }
+
+        let (p, u) = extrapolate(r, v, vec2(10.0, 0.0), 2.0);
+        assert!((p - vec2(180.0, 30.0)).length() < 1e-12);
+        assert!((u - vec2(50.0, 40.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn idle_turns_to_idle_heading_and_brakes() {
+        let mut ship = Ship::new();
+        ship.config.idle_heading = IdleHeading::Fixed(1.0);
+        let velocity = vec2(50.0, -20.0);
+
+        let (control, accel) = ship.idle_command(vec2(0.0, 0.0), velocity, 0.0, TICK_LENGTH);
+        assert!(control > 0.0); // Counter-clockwise toward +1 rad.
+        assert!(accel.dot(velocity) < 0.0);
+
+        ship.config.idle_decelerate = false;
+        let (_, accel) = ship.idle_command(vec2(0.0, 0.0), velocity, 0.0, TICK_LENGTH);
+        assert_eq!(accel, vec2(0.0, 0.0));
+
+        // Facing the arena centre from +x means turning to pi.
+        ship.config.idle_heading = IdleHeading::ArenaCenter;
+        ship.pid.reset();
+        let (control, _) = ship.idle_command(vec2(1000.0, 0.0), velocity, 3.0, TICK_LENGTH);
+        assert!(control > 0.0);
+    }
+
+    #[test]
+    fn friendly_on_firing_line_blocks_shot() {
+        let origin = vec2(0.0, 0.0);
+        let bullet_velocity = vec2(BULLET_SPEED, 0.0);
+        let max_range = BULLET_SPEED * MAX_FLIGHT_TIME;
+
+        let on_line = [(vec2(500.0, 5.0), 20.0)];
+        assert!(line_of_fire_blocked(
+            origin,
+            bullet_velocity,
+            MAX_FLIGHT_TIME,
+            &on_line
+        ));
+
+        let off_side = [(vec2(500.0, 200.0), 20.0)];
+        assert!(!line_of_fire_blocked(
+            origin,
+            bullet_velocity,
+            MAX_FLIGHT_TIME,
+            &off_side
+        ));
+
+        let behind = [(vec2(-500.0, 0.0), 20.0)];
+        assert!(!line_of_fire_blocked(
+            origin,
+            bullet_velocity,
+            MAX_FLIGHT_TIME,
+            &behind
+        ));
+
+        let beyond_range = [(vec2(2.0 * max_range, 0.0), 20.0)];
+        assert!(!line_of_fire_blocked(
+            origin,
+            bullet_velocity,
+            MAX_FLIGHT_TIME,
+            &beyond_range
+        ));
+    }
+
+    #[test]
+    fn ship_velocity_bends_line_of_fire() {
+        // Firing along +x while sliding along +y at 400 m/s carries the round
+        // onto a friendly that is off the barrel's line, and off one that is on it.
+        let origin = vec2(0.0, 0.0);
+        let bullet_velocity = vec2(BULLET_SPEED, 0.0) + vec2(0.0, 400.0);
+
+        let on_drift = [(vec2(1000.0, 400.0), 20.0)];
+        assert!(line_of_fire_blocked(
+            origin,
+            bullet_velocity,
+            MAX_FLIGHT_TIME,
+            &on_drift
+        ));
+
+        let on_barrel = [(vec2(1000.0, 0.0), 20.0)];
+        assert!(!line_of_fire_blocked(
+            origin,
+            bullet_velocity,
+            MAX_FLIGHT_TIME,
+            &on_barrel
+        ));
+    }
+
+    #[test]
+    fn aim_setpoint_slew_is_rate_limited() {
+        let mut ship = Ship::new();
+        let max_step = 5.0_f64.to_radians();
+        ship.config.max_aim_rate = max_step / TICK_LENGTH;
+
+        let mut prev = ship.aim_angle(vec2(1000.0, 0.0), TICK_LENGTH);
+        for k in 1..20 {
+            // Raw aim heading swings +/-30 degrees every tick.
+            let raw = (if k % 2 == 0 { 30.0_f64 } else { -30.0 }).to_radians();
+            let angle = ship.aim_angle(vec2(raw.cos(), raw.sin()) * 1000.0, TICK_LENGTH);
+            assert!(angle_diff(prev, angle).abs() <= max_step + 1e-12);
+            prev = angle;
+        }
+    }
+
+    #[test]
+    fn accel_smoothing_is_tick_rate_independent() {
+        let step = vec2(100.0, 0.0);
+        let response = |dt: f64, ticks: usize| {
+            let mut ship = Ship::new();
+            for _ in 0..ticks {
+                ship.smooth_accel(step, dt);
+            }
+            ship.accel_command
+        };
+
+        // Half a second of simulated time at 60 Hz and at 240 Hz.
+        let coarse = response(1.0 / 60.0, 30);
+        let fine = response(1.0 / 240.0, 120);
+        assert!((coarse - fine).length() < 1e-9);
+    }
+
+    #[test]
+    fn staggered_guns_interleave() {
+        let reload = 10;
+        let mut last_fired = [None, None];
+        let mut shots = [Vec::new(), Vec::new()];
+        for tick in 0..60 {
+            let guns = staggered_fire(tick, reload, last_fired);
+            assert!(!(guns[0] && guns[1]));
+            for (gun, &shoot) in guns.iter().enumerate() {
+                if shoot {
+                    shots[gun].push(tick);
+                    last_fired[gun] = Some(tick);
+                }
+            }
+        }
+
+        assert_eq!(shots[0], vec![0, 10, 20, 30, 40, 50]);
+        assert_eq!(shots[1], vec![5, 15, 25, 35, 45, 55]);
+    }
+
+    #[test]
+    fn slew_time_to_bring_target_into_arc() {
+        let quarter_turn = std::f64::consts::FRAC_PI_2;
+
+        let t = slew_time(0.0, quarter_turn, 0.0, quarter_turn).unwrap();
+        assert!((t - 1.0).abs() < 1e-12);
+
+        // A 0.1 rad arc takes 0.1 rad off the slew.
+        let t = slew_time(0.0, quarter_turn, 0.1, quarter_turn).unwrap();
+        assert!((t - (quarter_turn - 0.1) / quarter_turn).abs() < 1e-12);
+
+        assert_eq!(slew_time(0.0, 0.05, 0.1, quarter_turn), Some(0.0));
+        assert_eq!(slew_time(0.0, quarter_turn, 0.1, 0.0), None);
+    }
+
+    #[test]
+    fn low_ammo_raises_fire_threshold() {
+        let capacity = 100;
+        let thresholds: Vec<f64> = [100, 75, 50, 25, 0]
+            .iter()
+            .map(|&remaining| min_hit_prob_for_ammo(remaining, capacity))
+            .collect();
+        assert!(thresholds.windows(2).all(|w| w[1] > w[0]));
+
+        // A marginal shot is taken with a full magazine but not a nearly empty one.
+        let marginal = hit_probability(0.7 * FIRE_TOLERANCE, FIRE_TOLERANCE);
+        let mut ship = Ship::new();
+        assert!(ship.ammo_allows(marginal));
+        ship.load_ammo(capacity);
+        assert!(ship.ammo_allows(marginal));
+        // With a full magazine the gate is exactly the plain tolerance.
+        assert!(ship.fire_allowed(0.999 * FIRE_TOLERANCE));
+        assert!(!ship.fire_allowed(FIRE_TOLERANCE));
+        ship.ammo_remaining = 10;
+        assert!(!ship.ammo_allows(marginal));
+        assert!(ship.ammo_allows(hit_probability(0.0, FIRE_TOLERANCE)));
+
+        ship.ammo_remaining = 0;
+        assert!(!ship.ammo_allows(1.0));
+    }
+
+    #[test]
+    fn missile_cooldown_prevents_back_to_back_launches() {
+        let mut ship = Ship::new();
+        let range = 2.0 * ship.config.missile_min_range;
+        assert!(!ship.missile_launch_ready(range, 0));
+
+        ship.config.missile_weapon = Some(1);
+        assert!(ship.missile_launch_ready(range, 0));
+        assert!(!ship.missile_launch_ready(0.5 * ship.config.missile_min_range, 0));
+
+        ship.last_missile_launch = Some(0);
+        assert!(!ship.missile_launch_ready(range, 1));
+        assert!(!ship.missile_launch_ready(range, ship.config.missile_cooldown_ticks - 1));
+        assert!(ship.missile_launch_ready(range, ship.config.missile_cooldown_ticks));
+    }
+
+    #[test]
+    fn missile_guidance_stops_after_flight_time() {
+        let mut ship = Ship::new();
+        assert!(!ship.missile_in_flight(0));
+
+        ship.last_missile_launch = Some(100);
+        let flight = ship.config.missile_flight_ticks;
+        assert!(ship.missile_in_flight(100));
+        assert!(ship.missile_in_flight(100 + flight - 1));
+        assert!(!ship.missile_in_flight(100 + flight));
+    }
+
+    #[test]
+    fn missile_message_layout() {
+        let message = missile_message(vec2(1.0, 2.0), vec2(3.0, 4.0));
+        assert_eq!(message, [1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn wall_avoidance_pushes_away_near_wall() {
+        let (world_size, margin, max_accel) = (10_000.0, 1000.0, 60.0);
+        let near_east_wall = vec2(4950.0, 0.0);
+        let into_wall = vec2(1e5, 2e4);
+        let accel = wall_avoidance(into_wall, near_east_wall, world_size, margin, max_accel);
+        assert!(accel.x < 0.0);
+        assert!(accel.length() <= max_accel + 1e-9);
+
+        // Closer to the wall, the push outward is stronger.
+        let nearer = wall_avoidance(into_wall, vec2(4990.0, 0.0), world_size, margin, max_accel);
+        assert!(nearer.x < accel.x);
+
+        // In a corner the push is diagonal, away from both walls.
+        let corner = wall_avoidance(
+            vec2(0.0, 0.0),
+            vec2(-4950.0, -4950.0),
+            world_size,
+            margin,
+            max_accel,
+        );
+        assert!(corner.x > 0.0 && corner.y > 0.0);
+    }
+
+    #[test]
+    fn wall_avoidance_leaves_command_unchanged_far_from_walls() {
+        let desired = vec2(1e5, -3e4);
+        let accel = wall_avoidance(desired, vec2(1000.0, -2000.0), 10_000.0, 1000.0, 60.0);
+        assert_eq!(accel, desired);
+    }
+
+    #[test]
+    fn custom_config_is_used() {
+        let config = ShipConfig {
+            heading_kp: 12.0,
+            heading_ki: 0.5,
+            heading_kd: 3.0,
+            fire_tolerance: 30.0,
+            accel_gain: 200.0,
+            ..ShipConfig::default()
+        };
+        let mut ship = Ship::with_config(config.clone());
+        assert_eq!(ship.config(), &config);
+        assert_eq!(Ship::new().config(), &ShipConfig::default());
+
+        // A 20 m miss is outside the default gate but inside the custom one.
+        assert!(!Ship::new().fire_allowed(20.0));
+        assert!(ship.fire_allowed(20.0));
+
+        let mut pid = Pid::new(12.0, 0.5, 3.0);
+        assert_eq!(ship.pid.update(0.1, 0.1), pid.update(0.1, 0.1));
+    }
+
+    #[test]
+    fn position_control_settles_without_overshoot() {
+        let mut ship = Ship::new();
+        let desired = vec2(60.0, 80.0);
+        let (mut position, mut velocity) = (vec2(0.0, 0.0), vec2(0.0, 0.0));
+        let dt = 1.0 / 60.0;
+
+        let mut prev_error = (desired - position).length();
+        for _ in 0..(15.0 / dt) as usize {
+            let accel = ship.position_command(position, desired, dt);
+            assert!(accel.length() <= ship.config().max_accel + 1e-9);
+            velocity += accel * dt;
+            position += velocity * dt;
+
+            let error = (desired - position).length();
+            assert!(error <= prev_error);
+            // Never past the set-point along the approach direction.
+            assert!((desired - position).dot(desired) >= 0.0);
+            prev_error = error;
         }
-        let t = self.target.time_to_intercept.unwrap();
-        let aim_point_rel = self.target.intercept_point.unwrap() - position();
-        let shot_distance = BULLET_SPEED * t;
+        assert!(prev_error < 1.0);
+    }
+
+    #[test]
+    fn aim_accel_feedforward_reduces_lag_on_crosser() {
+        // A target crossing 500 m ahead at 300 m/s; the heading is a unit-inertia
+        // body whose angular acceleration is capped like a fighter's. Returns the
+        // worst heading error over the pass.
+        let worst_heading_error = |feedforward: f64, dt: f64| {
+            let mut ship = Ship::with_config(ShipConfig {
+                aim_accel_feedforward: feedforward,
+                ..ShipConfig::default()
+            });
+            let (range, speed, x0) = (500.0, 300.0, -1500.0);
+            let max_angular_accel = std::f64::consts::TAU;
+            let target_velocity = vec2(speed, 0.0);
+
+            let mut heading = vec2(x0, range).angle();
+            let mut angular_velocity = aim_rate(vec2(x0, range), target_velocity);
+            let mut worst: f64 = 0.0;
+            for tick in 0..=(-2.0 * x0 / speed / dt).round() as usize {
+                let r_rel = vec2(x0 + speed * tick as f64 * dt, range);
+                let error = angle_diff(heading, r_rel.angle());
+                worst = worst.max(error.abs());
+                let accel = aim_accel(r_rel, target_velocity, vec2(0.0, 0.0));
+                let torque = ship
+                    .heading_torque(error, accel, dt)
+                    .clamp(-max_angular_accel, max_angular_accel);
+                angular_velocity += torque * dt;
+                heading += angular_velocity * dt;
+            }
+            worst
+        };
+
+        // The same gain gives the same improvement at 60 Hz and 240 Hz.
+        let mut improvements = vec![];
+        for dt in [1.0 / 60.0, 1.0 / 240.0] {
+            assert_eq!(
+                worst_heading_error(0.0, dt),
+                worst_heading_error(ShipConfig::default().aim_accel_feedforward, dt)
+            );
+            let pid_only = worst_heading_error(0.0, dt);
+            let with_feedforward = worst_heading_error(1.0, dt);
+            assert!(with_feedforward < 0.5 * pid_only);
+            improvements.push(with_feedforward / pid_only);
+        }
+        assert!((improvements[0] - improvements[1]).abs() < 0.1);
+    }
+
+    #[test]
+    fn aim_accel_matches_differenced_aim_rate() {
+        let (r, v, a) = (vec2(-800.0, 500.0), vec2(300.0, -20.0), vec2(15.0, 40.0));
+        let h = 1e-4;
+        let rate_at = |t: f64| aim_rate(r + v * t + 0.5 * a * t * t, v + a * t);
+        let differenced = (rate_at(h) - rate_at(-h)) / (2.0 * h);
+        assert!((aim_accel(r, v, a) - differenced).abs() < 1e-9);
+        assert_eq!(aim_accel(vec2(0.0, 0.0), v, a), 0.0);
+    }
+
+    #[test]
+    fn aim_rate_of_crossing_point() {
+        // Abeam at 500 m, crossing at 300 m/s: bearing turns at 300 / 500 rad/s.
+        assert!((aim_rate(vec2(0.0, 500.0), vec2(300.0, 0.0)) + 0.6).abs() < 1e-12);
+        assert_eq!(aim_rate(vec2(1000.0, 0.0), vec2(-300.0, 0.0)), 0.0);
+        assert_eq!(aim_rate(vec2(0.0, 0.0), vec2(300.0, 0.0)), 0.0);
+    }
 
-        debug!("t: {}", t);
-        draw_diamond(position() + aim_point_rel, 10.0, 0x00ff00);
-        draw_line(
-            position(),
-            position() + vec2(heading().cos(), heading().sin()) * BULLET_SPEED * t,
-            0x00ff00,
+    #[test]
+    fn fires_weapon_with_best_solution() {
+        let slow = Weapon {
+            index: 0,
+            speed: 1000.0,
+            max_flight_time: 10.0,
+        };
+        let fast = Weapon {
+            index: 1,
+            speed: 4000.0,
+            max_flight_time: 10.0,
+        };
+        let mut ship = Ship::with_config(ShipConfig {
+            weapons: vec![slow, fast],
+            ..ShipConfig::default()
+        });
+        ship.target = Target::new(vec2(3000.0, 500.0), vec2(-50.0, 80.0), vec2(0.0, 0.0));
+
+        let (weapon, solution) = ship
+            .best_weapon_solution(vec2(0.0, 0.0), vec2(0.0, 0.0))
+            .unwrap();
+        assert_eq!(weapon, fast);
+        // The target is left solved for the chosen weapon.
+        assert_eq!(ship.target.firing_solution(), Some(solution));
+        assert!((solution.range() - fast.speed * solution.time_to_intercept).abs() < 1e-3);
+
+        // A projectile that expires before reaching the target is never chosen.
+        let short_lived = Weapon {
+            max_flight_time: 0.5,
+            ..fast
+        };
+        ship.config.weapons = vec![slow, short_lived];
+        let (weapon, solution) = ship
+            .best_weapon_solution(vec2(0.0, 0.0), vec2(0.0, 0.0))
+            .unwrap();
+        assert_eq!(weapon, slow);
+        // Still left solved for the winner, though another weapon was solved last.
+        assert_eq!(ship.target.firing_solution(), Some(solution));
+        assert_eq!(ship.target.max_flight_time, slow.max_flight_time);
+    }
+
+    #[test]
+    fn staggered_pair_shares_solved_speed() {
+        let gun = |index: usize, speed: f64| Weapon {
+            index,
+            speed,
+            max_flight_time: MAX_FLIGHT_TIME,
+        };
+        let weapons = [
+            gun(0, 1000.0),
+            gun(1, 4000.0),
+            gun(2, 1000.0),
+            gun(3, 1000.0),
+        ];
+        assert_eq!(
+            staggered_pair(&weapons, weapons[0]),
+            Some([weapons[0], weapons[2]])
         );
+        assert_eq!(staggered_pair(&weapons, weapons[1]), None);
+        assert_eq!(staggered_pair(&weapons[..2], weapons[0]), None);
+    }
 
-        let aim_angle = aim_point_rel.angle();
+    #[test]
+    fn standoff_point_is_finite_on_target() {
+        let target = vec2(100.0, 200.0);
+        let hold = standoff_point(vec2(100.0, 500.0), 0.0, target, 1000.0);
+        assert!((hold - vec2(100.0, 1200.0)).length() < 1e-9);
 
-        let heading_rel_error = angle_diff(heading(), aim_angle);
+        let hold = standoff_point(target, 0.0, target, 1000.0);
+        assert!((hold - vec2(-900.0, 200.0)).length() < 1e-9);
+    }
 
-        // --- PID heading control ---
-        let control = self.pid.update(heading_rel_error, TICK_LENGTH);
-        torque(control);
+    #[test]
+    fn idle_resets_position_loops() {
+        let dt = 1.0 / 60.0;
+        let mut ship = Ship::new();
+        ship.position_command(vec2(0.0, 0.0), vec2(0.0, 0.0), dt);
+        ship.idle_command(vec2(0.0, 0.0), vec2(0.0, 0.0), 0.0, dt);
+
+        // A new set-point after the idle gap gets no derivative kick.
+        let desired = vec2(30.0, 0.0);
+        let resumed = ship.position_command(vec2(0.0, 0.0), desired, dt);
+        let fresh = Ship::new().position_command(vec2(0.0, 0.0), desired, dt);
+        assert_eq!(resumed, fresh);
+    }
 
-        // Fire
-        if heading_rel_error.abs() * shot_distance < 10.0 {
-            fire(0);
+    /// Sensors for a ship at rest at the origin facing +x on `tick`, with the
+    /// target at `target_position`.
+    fn sensors_at(tick: u32, target_position: Vec2) -> Sensors {
+        Sensors {
+            position: vec2(0.0, 0.0),
+            velocity: vec2(0.0, 0.0),
+            heading: 0.0,
+            tick,
+            target_position,
+            target_velocity: vec2(0.0, 0.0),
+            world_size: 40_000.0,
+            accel_limits: FIGHTER_ACCEL_LIMITS,
         }
+    }
+
+    const FIGHTER_ACCEL_LIMITS: AccelLimits = AccelLimits {
+        forward: 60.0,
+        backward: 30.0,
+        lateral: 30.0,
+    };
+
+    #[test]
+    fn holding_aim_through_reload_costs_one_round() {
+        let mut ship = Ship::new();
+        ship.set_debug(false);
+        ship.load_ammo(100);
+        let reload_period = 10;
+
+        // The gun reloads for `reload_period` ticks after each shot; the ship
+        // stays on target and asks to fire every tick.
+        let mut reload = 0;
+        let mut shots = vec![];
+        for tick in 0..=reload_period {
+            let left = reload;
+            let commands = ship.step(sensors_at(tick, vec2(1000.0, 0.0)), TICK_LENGTH, |_| left);
+            if commands.fire.is_empty() {
+                reload = reload.saturating_sub(1);
+            } else {
+                shots.push(tick);
+                reload = reload_period - 1;
+            }
+            if tick < reload_period {
+                assert_eq!(ship.ammo_remaining, 99);
+            }
+        }
+        assert_eq!(shots, vec![0, reload_period]);
+        assert_eq!(ship.ammo_remaining, 98);
+    }
+
+    #[test]
+    fn hull_limits_each_body_axis() {
+        let limits = FIGHTER_ACCEL_LIMITS;
+        let accel = hull_limited(vec2(1e6, -1e6), 0.0, limits);
+        assert_eq!(accel, vec2(60.0, -30.0));
+
+        // Facing +y, a command along -y is braking and capped by `backward`.
+        let heading = std::f64::consts::FRAC_PI_2;
+        let accel = hull_limited(vec2(10.0, -1e6), heading, limits);
+        assert!((accel - vec2(10.0, -30.0)).length() < 1e-9);
+
+        let within = vec2(20.0, 10.0);
+        assert!((hull_limited(within, 0.3, limits) - within).length() < 1e-9);
+    }
+
+    #[test]
+    fn applied_accel_is_what_the_hull_delivers() {
+        let mut ship = Ship::new();
+        ship.set_debug(false);
+
+        // A target 1 km ahead asks for about 1e6 m/s^2 with the default gain.
+        let commands = ship.step(sensors_at(0, vec2(1000.0, 0.0)), TICK_LENGTH, |_| 0);
+        assert_eq!(commands.accel, vec2(60.0, 0.0));
+        assert_eq!(ship.accel_command, commands.accel);
+
+        let (_, predicted_velocity) =
+            extrapolate(vec2(0.0, 0.0), vec2(0.0, 0.0), ship.accel_command, 1.0);
+        assert_eq!(predicted_velocity, vec2(60.0, 0.0));
+    }
+
+    #[test]
+    fn aim_accel_feedforward_uses_applied_own_accel() {
+        let torques = |feedforward: f64| {
+            let mut ship = Ship::with_config(ShipConfig {
+                aim_accel_feedforward: feedforward,
+                ..ShipConfig::default()
+            });
+            ship.set_debug(false);
+            // Abeam to port the ship asks for ~1e6 m/s^2 sideways, but the
+            // hull only delivers its 30 m/s^2 lateral limit.
+            ship.step(sensors_at(0, vec2(0.0, 1000.0)), TICK_LENGTH, |_| 0);
+            let commands = ship.step(sensors_at(1, vec2(1000.0, 0.0)), TICK_LENGTH, |_| 0);
+            commands.torque
+        };
+
+        // Our 30 m/s^2 across a 1 km line of sight turns the bearing at
+        // -0.03 rad/s^2; the 1e6 m/s^2 request would have given -150.
+        let feedforward = torques(1.0) - torques(0.0);
+        assert!((feedforward + 0.03).abs() < 1e-9);
+    }
+
+    #[test]
+    fn no_shot_while_aim_setpoint_is_slewing() {
+        let mut ship = Ship::new();
+        ship.set_debug(false);
+        let range = 1000.0;
+        let at_bearing = |bearing: f64| vec2(bearing.cos(), bearing.sin()) * range;
+        let commands = ship.step(sensors_at(0, at_bearing(0.0)), TICK_LENGTH, |_| 0);
+        assert_eq!(commands.fire, vec![0]);
+
+        // The lead jumps 0.5 rad; the setpoint moves one rate-limited step and
+        // the ship is lined up with it, but not with the target.
+        let setpoint = ship.config.max_aim_rate * TICK_LENGTH;
+        let sensors = Sensors {
+            heading: setpoint,
+            ..sensors_at(1, at_bearing(0.5))
+        };
+        let commands = ship.step(sensors, TICK_LENGTH, |_| 0);
+        assert!(commands.fire.is_empty());
+
+        // Lined up with the target itself, it fires though the setpoint still lags.
+        let sensors = Sensors {
+            heading: 0.5,
+            ..sensors_at(2, at_bearing(0.5))
+        };
+        let commands = ship.step(sensors, TICK_LENGTH, |_| 0);
+        assert!(angle_diff(ship.last_aim_angle.unwrap(), 0.5) > 0.1);
+        assert_eq!(commands.fire, vec![0]);
+    }
+
+    #[test]
+    fn idle_clears_stale_aim_angle() {
+        let mut ship = Ship::new();
+        ship.aim_angle(vec2(1000.0, 0.0), TICK_LENGTH);
+        ship.idle_command(vec2(0.0, 0.0), vec2(0.0, 0.0), 0.0, TICK_LENGTH);
+
+        // Back on target, the aim starts from the new bearing, even one too
+        // close to hold, instead of slewing from or holding the old angle.
+        let bearing: f64 = 2.0;
+        let close = vec2(bearing.cos(), bearing.sin()) * 0.5 * ship.config.min_aim_distance;
+        assert!((ship.aim_angle(close, TICK_LENGTH) - bearing).abs() < 1e-9);
+    }
+
+    #[test]
+    fn friendly_beyond_weapon_reach_does_not_block() {
+        // A friendly 5 km ahead is out of reach of a 1 s round but not a 10 s one.
+        let friendlies = [(vec2(5000.0, 0.0), 20.0)];
+        let short_range = Weapon {
+            index: 0,
+            speed: BULLET_SPEED,
+            max_flight_time: 1.0,
+        };
+        let long_range = Weapon {
+            index: 1,
+            max_flight_time: MAX_FLIGHT_TIME,
+            ..short_range
+        };
+        let (muzzle, ship_velocity) = (vec2(0.0, 0.0), vec2(0.0, 0.0));
+        assert!(!weapon_line_blocked(
+            short_range,
+            muzzle,
+            ship_velocity,
+            0.0,
+            &friendlies
+        ));
+        assert!(weapon_line_blocked(
+            long_range,
+            muzzle,
+            ship_velocity,
+            0.0,
+            &friendlies
+        ));
 
-        accelerate(1000.0 * aim_point_rel);
+        // Both solve alike, so the first, short-range gun fires; the long-range
+        // gun's reach doesn't hold it.
+        let mut ship = Ship::with_config(ShipConfig {
+            weapons: vec![short_range, long_range],
+            ..ShipConfig::default()
+        });
+        ship.set_debug(false);
+        ship.friendlies = friendlies.to_vec();
+        let commands = ship.step(sensors_at(0, vec2(500.0, 0.0)), TICK_LENGTH, |_| 0);
+        assert_eq!(commands.fire, vec![0]);
     }
 }
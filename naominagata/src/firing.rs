@@ -0,0 +1,431 @@
+//! Intercept solvers behind [`Target`](crate::target::Target)'s firing
+//! solutions: given a target's position, velocity and acceleration (or turn
+//! rate) relative to the shooter, when can a bullet fired now at a fixed
+//! speed meet it?
+use crate::target::FiringSolution;
+use oort_api::prelude::*;
+
+/// Iteration cap for the bisection and bracketing searches.
+const MAX_ITER: usize = 100;
+/// Newton steps used to polish an analytic intercept root.
+const NEWTON_REFINE_ITER: usize = 3;
+/// Largest miss distance (m) at which an intercept root is still accepted.
+const RESIDUAL_TOL: f64 = 1e-3;
+/// Bracketing step (s) for the constant-turn-rate solver.
+const CTR_STEP: f64 = 0.05;
+
+/// Intercept a target moving with constant acceleration, using
+/// a constant speed bullet in 2d. Position and velocity are
+/// relative to the shooter.
+//
+/// r, v, a: initial position, velocity, acceleration of target
+/// u: bullet velocity, |u| = bullet_speed
+/// t: time to intercept
+///
+/// Governing equation:
+///    r + v t + 0.5 a t^2 = u t
+///
+/// Returns the earliest positive intercept time, or `None` if there is none
+/// within `max_time` (the bullet's lifetime) or the root doesn't actually
+/// close the geometry to within `RESIDUAL_TOL`.
+pub(crate) fn firing_solution_const_accel(
+    r_rel: Vec2,
+    v_rel: Vec2,
+    a_rel: Vec2,
+    bullet_speed: f64,
+    max_time: f64,
+) -> Option<f64> {
+    intercept_times_const_accel(r_rel, v_rel, a_rel, bullet_speed, max_time)
+        .into_iter()
+        .next()
+}
+
+/// Every valid intercept time up to `max_time` for the constant-acceleration
+/// geometry of `firing_solution_const_accel`, earliest first. A target that
+/// crosses the bullet's reach can be met both on the way in and on the way out.
+pub(crate) fn intercept_times_const_accel(
+    r_rel: Vec2,
+    v_rel: Vec2,
+    a_rel: Vec2,
+    bullet_speed: f64,
+    max_time: f64,
+) -> Vec<f64> {
+    let p4 = 0.25 * a_rel.dot(a_rel);
+    let p3 = v_rel.dot(a_rel);
+    let p2 = v_rel.dot(v_rel) + r_rel.dot(a_rel) - bullet_speed * bullet_speed;
+    let p1 = 2.0 * r_rel.dot(v_rel);
+    let p0 = r_rel.dot(r_rel);
+    let coeffs = [p0, p1, p2, p3, p4];
+
+    // Solve at^4 + bt^3 + ct^2 + dt + e = 0 for all real roots rather than
+    // Newton from a guess, which can diverge or land on a later (or negative)
+    // root when the previous tick's solution is stale.
+    real_roots(&coeffs)
+        .into_iter()
+        // Roots beyond bullet reach aren't worth refining.
+        .filter(|&t| t > 0.0 && t <= max_time)
+        .map(|t| refine_root(&coeffs, t))
+        // Reject phantom roots: the bullet must actually arrive where the target is.
+        .filter(|&t| {
+            let miss = (r_rel + v_rel * t + 0.5 * a_rel * t * t).length() - bullet_speed * t;
+            miss.abs() <= RESIDUAL_TOL
+        })
+        .collect()
+}
+
+/// Polishes a root of the polynomial `c` with Newton, keeping only steps
+/// that reduce the residual.
+fn refine_root(c: &[f64], mut t: f64) -> f64 {
+    let derivative: Vec<f64> = c
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(k, &ck)| k as f64 * ck)
+        .collect();
+    let mut f = eval_poly(c, t);
+    for _ in 0..NEWTON_REFINE_ITER {
+        let df = eval_poly(&derivative, t);
+        if df.abs() < 1e-6 {
+            break; // Avoid division by zero
+        }
+        let t_next = t - f / df;
+        let f_next = eval_poly(c, t_next);
+        if f_next.abs() >= f.abs() {
+            break; // Converged
+        }
+        (t, f) = (t_next, f_next);
+    }
+    t
+}
+
+/// Evaluates `c[0] + c[1] t + ... + c[n] t^n` by Horner's rule.
+fn eval_poly(c: &[f64], t: f64) -> f64 {
+    c.iter().rev().fold(0.0, |acc, &ck| acc * t + ck)
+}
+
+/// All real roots of `c[0] + c[1] t + ... + c[n] t^n`, in ascending order.
+///
+/// Between consecutive stationary points (the real roots of the derivative,
+/// found recursively) the polynomial is monotonic, so each such interval, out
+/// to the Cauchy bound, holds at most one root, found by bisection. Repeated
+/// roots, like a grazing intercept, don't change sign and are instead picked up
+/// as stationary points where the polynomial vanishes.
+fn real_roots(c: &[f64]) -> Vec<f64> {
+    // Drop vanishing leading coefficients, e.g. the quartic terms when a = 0.
+    let scale = c.iter().fold(0.0_f64, |m, ck| m.max(ck.abs()));
+    let mut n = c.len().saturating_sub(1);
+    while n > 0 && c[n].abs() <= 1e-12 * scale {
+        n -= 1;
+    }
+    match n {
+        0 => return Vec::new(),
+        1 => return vec![-c[0] / c[1]],
+        _ => {}
+    }
+    let c = &c[..=n];
+
+    let derivative: Vec<f64> = c
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(k, &ck)| k as f64 * ck)
+        .collect();
+    let bound = 1.0
+        + c[..n]
+            .iter()
+            .fold(0.0_f64, |m, &ck| m.max((ck / c[n]).abs()));
+    let mut points: Vec<f64> = real_roots(&derivative)
+        .into_iter()
+        .filter(|t| t.abs() < bound)
+        .collect();
+    points.push(bound);
+
+    let mut roots = Vec::new();
+    let (mut lo, mut f_lo, mut lo_is_root) = (-bound, eval_poly(c, -bound), false);
+    for (k, &t) in points.iter().enumerate() {
+        let f_t = eval_poly(c, t);
+        // Only stationary points can be repeated roots; compare against the
+        // size of the terms to allow for rounding.
+        let magnitude = c
+            .iter()
+            .rev()
+            .fold(0.0, |acc, &ck| acc * t.abs() + ck.abs());
+        let t_is_root = k + 1 < points.len() && f_t.abs() <= 1e-9 * magnitude;
+
+        if !lo_is_root && !t_is_root && (f_lo < 0.0) != (f_t < 0.0) {
+            let (mut a, mut b, mut f_a) = (lo, t, f_lo);
+            for _ in 0..MAX_ITER {
+                let mid = 0.5 * (a + b);
+                let f_mid = eval_poly(c, mid);
+                if (f_mid < 0.0) == (f_a < 0.0) {
+                    (a, f_a) = (mid, f_mid);
+                } else {
+                    b = mid;
+                }
+                if b - a <= 1e-12 * (1.0 + mid.abs()) {
+                    break; // Converged
+                }
+            }
+            roots.push(0.5 * (a + b));
+        }
+        if t_is_root {
+            roots.push(t);
+        }
+        (lo, f_lo, lo_is_root) = (t, f_t, t_is_root);
+    }
+    roots
+}
+
+/// Computes an intercept firing solution assuming constant velocities for both
+/// the ship and the target.
+///
+/// The calculation solves the classic pursuit problem in 2-D by determining
+/// the earliest positive time `t` at which a bullet—shot today at constant
+/// speed `bullet_speed`—can meet the target.  If no positive‐time solution
+/// exists (i.e. the discriminant is negative or both roots are non-positive),
+/// `None` is returned.
+///
+/// Returns the time‐to‐impact `t` (seconds) together with the **relative** aim
+/// point, expressed in the ship-centred coordinate frame (`r_rel + v_rel·t`).
+pub(crate) fn firing_solution_const_vel(
+    r_rel: Vec2,
+    v_rel: Vec2,
+    bullet_speed: f64,
+) -> Option<(f64, Vec2)> {
+    // Quadratic coefficients for |r_rel + v_rel·t| = bullet_speed·t.
+    let a = v_rel.dot(v_rel) - bullet_speed * bullet_speed;
+    let b = 2.0 * v_rel.dot(r_rel);
+    let c = r_rel.dot(r_rel);
+
+    // Discriminant of the quadratic.
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return None;
+    }
+
+    let sqrt_disc = disc.sqrt();
+    let (t1, t2) = ((-b - sqrt_disc) / (2.0 * a), (-b + sqrt_disc) / (2.0 * a));
+
+    // Earliest positive interception time.
+    let t = match (t1 > 0.0, t2 > 0.0) {
+        (true, true) => t1.min(t2),
+        (true, false) => t1,
+        (false, true) => t2,
+        _ => return None,
+    };
+
+    Some((t, r_rel + v_rel * t))
+}
+
+/// Turn rate (rad/s) implied by two successive velocities `dt` seconds apart.
+pub fn turn_rate_from_velocities(prev_velocity: Vec2, velocity: Vec2, dt: f64) -> f64 {
+    angle_diff(prev_velocity.angle(), velocity.angle()) / dt
+}
+
+/// Position after `t` seconds of a target starting at `r` with velocity `v`
+/// and holding a constant turn rate (constant speed, circular arc).
+fn position_const_turn_rate(r: Vec2, v: Vec2, turn_rate: f64, t: f64) -> Vec2 {
+    if turn_rate.abs() < 1e-9 {
+        return r + v * t;
+    }
+    let (sin, cos) = (turn_rate * t).sin_cos();
+    let v_perp = vec2(-v.y, v.x);
+    r + (v * sin + v_perp * (1.0 - cos)) / turn_rate
+}
+
+/// Intercepts a target holding a constant turn rate (CTR), which unlike the
+/// constant-acceleration model stays on its arc for long flight times.
+///
+/// r, v: world-frame position and velocity of the target
+/// turn_rate: target angular velocity (rad/s), positive counter-clockwise
+/// observer: world-frame position of a stationary shooter
+/// max_flight_time: bullet lifetime (s)
+///
+/// There is no polynomial form, so the earliest root of
+/// `|p(t) - observer| - bullet_speed * t` is bracketed by stepping forward in
+/// time and then refined by bisection. Returns `None` if no intercept exists
+/// within `max_flight_time`.
+pub fn firing_solution_ctr(
+    r: Vec2,
+    v: Vec2,
+    turn_rate: f64,
+    bullet_speed: f64,
+    observer: Vec2,
+    max_flight_time: f64,
+) -> Option<FiringSolution> {
+    let miss = |t: f64| {
+        (position_const_turn_rate(r, v, turn_rate, t) - observer).length() - bullet_speed * t
+    };
+
+    let (mut lo, mut hi) = (0.0, CTR_STEP);
+    while miss(hi) > 0.0 {
+        if hi >= max_flight_time {
+            return None;
+        }
+        lo = hi;
+        hi = (hi + CTR_STEP).min(max_flight_time);
+    }
+    for _ in 0..MAX_ITER {
+        let mid = 0.5 * (lo + hi);
+        if miss(mid) > 0.0 {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+        if hi - lo < 1e-6 {
+            break; // Converged
+        }
+    }
+
+    let t = 0.5 * (lo + hi);
+    Some(FiringSolution {
+        time_to_intercept: t,
+        intercept_point: position_const_turn_rate(r, v, turn_rate, t),
+        observer,
+        observer_velocity: vec2(0.0, 0.0),
+        target_velocity: v.rotate(turn_rate * t),
+        residual: miss(t).abs(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BULLET_SPEED, MAX_FLIGHT_TIME};
+
+    #[test]
+    fn const_vel_and_const_accel_converge_on_same_intercept() {
+        let r = vec2(2000.0, 500.0);
+        let v = vec2(-150.0, 60.0);
+        let (t_cv, aim) = firing_solution_const_vel(r, v, BULLET_SPEED).unwrap();
+        let t_ca = firing_solution_const_accel(r, v, vec2(0.0, 0.0), BULLET_SPEED, MAX_FLIGHT_TIME)
+            .unwrap();
+        assert!((t_cv - t_ca).abs() < 1e-9);
+        assert!((aim.length() - BULLET_SPEED * t_cv).abs() < 1e-6);
+
+        let a = vec2(5.0, -20.0);
+        let t = firing_solution_const_accel(r, v, a, BULLET_SPEED, MAX_FLIGHT_TIME).unwrap();
+        let miss = (r + v * t + 0.5 * a * t * t).length() - BULLET_SPEED * t;
+        assert!(miss.abs() < RESIDUAL_TOL);
+    }
+
+    #[test]
+    fn no_solution_when_target_outruns_bullet() {
+        let (r, v) = (vec2(1000.0, 0.0), vec2(2000.0, 0.0));
+        assert_eq!(firing_solution_const_vel(r, v, BULLET_SPEED), None);
+        assert_eq!(
+            firing_solution_const_accel(r, v, vec2(10.0, 0.0), BULLET_SPEED, MAX_FLIGHT_TIME),
+            None
+        );
+        assert!(
+            intercept_times_const_accel(r, v, vec2(10.0, 0.0), BULLET_SPEED, MAX_FLIGHT_TIME)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn stale_guess_recovery() {
+        // Polishing from last tick's intercept time moves toward the true root.
+        let r = vec2(1000.0, 0.0);
+        let v = vec2(0.0, 100.0);
+        let a = vec2(0.0, 10.0);
+        let t = firing_solution_const_accel(r, v, a, BULLET_SPEED, MAX_FLIGHT_TIME).unwrap();
+        let coeffs = [
+            r.dot(r),
+            2.0 * r.dot(v),
+            v.dot(v) + r.dot(a) - BULLET_SPEED * BULLET_SPEED,
+            v.dot(a),
+            0.25 * a.dot(a),
+        ];
+        let stale = 1.2 * t;
+        assert!((refine_root(&coeffs, stale) - t).abs() < 1e-3 * (stale - t).abs());
+
+        // The solver itself takes no guess, so a stale late intercept can't
+        // pull it off the earliest root.
+        let v = vec2(-2.0 * BULLET_SPEED, 0.0);
+        let times =
+            intercept_times_const_accel(r, v, vec2(0.0, 0.0), BULLET_SPEED, MAX_FLIGHT_TIME);
+        assert_eq!(times.len(), 2);
+        assert!((times[0] - 1.0 / 3.0).abs() < 1e-9);
+        assert!((times[1] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn real_roots_of_quartic() {
+        // (t + 1)(t - 1)(t - 2)(t - 3)
+        let roots = real_roots(&[-6.0, 5.0, 5.0, -5.0, 1.0]);
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([-1.0, 1.0, 2.0, 3.0]) {
+            assert!((root - expected).abs() < 1e-9);
+        }
+        assert!(real_roots(&[1.0, 0.0, 1.0]).is_empty());
+    }
+
+    #[test]
+    fn const_accel_grazing_tangent() {
+        // A target faster than the bullet whose path the bullet can only just
+        // touch: the quadratic's discriminant is zero, a repeated root.
+        let r = vec2(1000.0, 0.0);
+        let v = vec2(-3.0_f64.sqrt() * BULLET_SPEED, BULLET_SPEED);
+        let t = firing_solution_const_accel(r, v, vec2(0.0, 0.0), BULLET_SPEED, MAX_FLIGHT_TIME)
+            .unwrap();
+        assert!((t - 1.0 / 3.0_f64.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn const_accel_picks_earlier_of_two_intercepts() {
+        // A fast target heading straight at us is met on the way in (t = 1/3)
+        // and again on the way out (t = 1); with a little acceleration both
+        // roots move slightly but the earlier one must win.
+        let r = vec2(1000.0, 0.0);
+        let v = vec2(-2.0 * BULLET_SPEED, 0.0);
+        let a = vec2(0.0, 10.0);
+        let t = firing_solution_const_accel(r, v, a, BULLET_SPEED, MAX_FLIGHT_TIME).unwrap();
+        assert!((t - 1.0 / 3.0).abs() < 1e-2);
+
+        let miss = (r + v * t + 0.5 * a * t * t).length() - BULLET_SPEED * t;
+        assert!(miss.abs() < 1e-6);
+    }
+
+    #[test]
+    fn ctr_beats_const_accel_against_circling_target() {
+        let r = vec2(3000.0, 0.0);
+        let v = vec2(0.0, 200.0);
+        let turn_rate = 0.5;
+        let a = vec2(-v.y, v.x) * turn_rate; // Instantaneous centripetal acceleration.
+        let observer = vec2(0.0, 0.0);
+
+        let ctr =
+            firing_solution_ctr(r, v, turn_rate, BULLET_SPEED, observer, MAX_FLIGHT_TIME).unwrap();
+        let ctr_truth = position_const_turn_rate(r, v, turn_rate, ctr.time_to_intercept);
+        let ctr_miss = (ctr.intercept_point - ctr_truth).length();
+        let ctr_range = (ctr.intercept_point - observer).length();
+        assert!((ctr_range - BULLET_SPEED * ctr.time_to_intercept).abs() < 1e-2);
+
+        let t_ca = firing_solution_const_accel(r, v, a, BULLET_SPEED, MAX_FLIGHT_TIME).unwrap();
+        let ca_point = r + v * t_ca + 0.5 * a * t_ca * t_ca;
+        let ca_truth = position_const_turn_rate(r, v, turn_rate, t_ca);
+        let ca_miss = (ca_point - ca_truth).length();
+
+        assert!(ctr_miss < 1e-6);
+        assert!(ca_miss > 10.0 * (ctr_miss + 1.0));
+    }
+
+    #[test]
+    fn ctr_rejects_intercept_beyond_max_flight_time() {
+        let observer = vec2(0.0, 0.0);
+        let (v, turn_rate) = (vec2(0.0, 50.0), 0.1);
+
+        let near = vec2(0.9 * BULLET_SPEED * MAX_FLIGHT_TIME, 0.0);
+        let solution =
+            firing_solution_ctr(near, v, turn_rate, BULLET_SPEED, observer, MAX_FLIGHT_TIME)
+                .unwrap();
+        assert!(solution.time_to_intercept <= MAX_FLIGHT_TIME);
+
+        let far = vec2(1.2 * BULLET_SPEED * MAX_FLIGHT_TIME, 0.0);
+        assert!(
+            firing_solution_ctr(far, v, turn_rate, BULLET_SPEED, observer, MAX_FLIGHT_TIME)
+                .is_none()
+        );
+    }
+}
@@ -1,7 +1,12 @@
-use oort_api::prelude::*;
+/// Projectile speed (m/s) of a fighter's gun.
+const BULLET_SPEED: f64 = 1000.0;
 
-const BULLET_SPEED: f64 = 1000.0; // m/s
+/// Default projectile lifetime (s); each weapon can set its own.
+const MAX_FLIGHT_TIME: f64 = 10.0; // s
 
+pub mod evasion;
 pub mod fighter;
+mod firing;
 pub mod pid;
 pub mod target;
+pub mod util;
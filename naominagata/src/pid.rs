@@ -1,3 +1,5 @@
+use crate::util::RingBuffer;
+
 /// PID Controller implementation for scalar signals.
 ///
 /// The controller computes a control effort based on a proportional–integral–derivative
@@ -13,14 +15,19 @@
 ///
 /// # Example
 /// ```rust
-/// use naominagata::Pid;
+/// use naominagata::pid::Pid;
 ///
 /// let mut pid = Pid::new(1.0, 0.1, 0.01);
 /// let control = pid.update(0.05, 0.016); // error = 0.05 rad, dt = 16 ms
 /// ```
 ///
-/// The implementation is intentionally minimal: no anti-windup, filtering, or clamping
-/// is performed. These can be added in a future refinement.
+/// By default the output is unbounded. [`Pid::with_limits`] clamps it to the
+/// actuator's range and freezes the integral while the output is saturated
+/// (conditional integration), so the integral cannot wind up. Likewise the
+/// derivative is raw unless [`Pid::with_derivative_filter`] adds a low-pass.
+///
+/// The last [`OUTPUT_HISTORY`] outputs are kept so that a sustained oscillation
+/// (e.g. from over-aggressive gains) can be detected with [`Pid::is_oscillating`].
 #[derive(Debug, Clone)]
 pub struct Pid {
     /// Proportional gain.
@@ -34,8 +41,21 @@ pub struct Pid {
     integral: f64,
     /// Error at the previous update; `None` until the first call.
     prev_error: Option<f64>,
+    /// Time constant (s) of the derivative low-pass; zero disables it.
+    derivative_tau: f64,
+    /// Low-passed derivative of the error.
+    filtered_derivative: f64,
+
+    /// Output clamp as `(min, max)`; `None` for an unbounded output.
+    limits: Option<(f64, f64)>,
+
+    /// Recent outputs, oldest first.
+    history: RingBuffer<f64, OUTPUT_HISTORY>,
 }
 
+/// Number of recent outputs kept for oscillation detection.
+pub const OUTPUT_HISTORY: usize = 16;
+
 impl Pid {
     /// Creates a new [`Pid`] controller with the provided gains.
     #[must_use]
@@ -46,13 +66,64 @@ impl Pid {
             kd,
             integral: 0.0,
             prev_error: None,
+            derivative_tau: 0.0,
+            filtered_derivative: 0.0,
+            limits: None,
+            history: RingBuffer::new(0.0),
         }
     }
 
-    /// Resets the internal integral term and derivative memory.
+    /// Clamps the output to `[min, max]` and enables anti-windup.
+    ///
+    /// # Panics
+    /// Panics if `min` is not less than `max`.
+    #[must_use]
+    pub fn with_limits(mut self, min: f64, max: f64) -> Self {
+        assert!(min < max, "min ({min}) must be < max ({max})");
+        self.limits = Some((min, max));
+        self
+    }
+
+    /// Passes the derivative through a first-order low-pass with time
+    /// constant `tau` (seconds) before scaling by `K_d`:
+    ///
+    /// ```text
+    /// d_filt += (d_raw - d_filt) * dt / (tau + dt)
+    /// ```
+    ///
+    /// A `tau` of zero leaves the derivative unfiltered.
+    ///
+    /// # Panics
+    /// Panics if `tau` is negative.
+    #[must_use]
+    pub fn with_derivative_filter(mut self, tau: f64) -> Self {
+        assert!(tau >= 0.0, "tau ({tau}) must be >= 0");
+        self.derivative_tau = tau;
+        self
+    }
+
+    /// Resets the internal integral term, derivative memory, and output history.
     pub fn reset(&mut self) {
         self.integral = 0.0;
         self.prev_error = None;
+        self.filtered_derivative = 0.0;
+        self.history.clear();
+    }
+
+    /// Returns `true` if the output has been flipping sign on at least half of
+    /// the last [`OUTPUT_HISTORY`] updates, which indicates the loop is ringing
+    /// rather than converging.
+    pub fn is_oscillating(&self) -> bool {
+        if !self.history.is_full() {
+            return false;
+        }
+        let sign_changes = self
+            .history
+            .iter()
+            .zip(self.history.iter().skip(1))
+            .filter(|(a, b)| a * b < 0.0)
+            .count();
+        sign_changes >= OUTPUT_HISTORY / 2
     }
 
     /// Updates the controller with the current `error` and time step `dt` (in seconds).
@@ -62,30 +133,52 @@ impl Pid {
     /// * `dt` - Time since the previous update in seconds. Must be positive.
     ///
     /// # Returns
-    /// The control effort computed from the PID algorithm.
+    /// The control effort computed from the PID algorithm, clamped to the
+    /// limits if any were set.
     ///
     /// # Panics
     /// Panics if `dt` is not strictly positive.
     pub fn update(&mut self, error: f64, dt: f64) -> f64 {
-        assert!(dt > 0.0, "dt ({}) must be > 0", dt);
+        assert!(dt > 0.0, "dt ({dt}) must be > 0");
 
         // Proportional term.
         let p = self.kp * error;
 
-        // Integral term.
-        self.integral += error * dt;
-        let i = self.ki * self.integral;
-
         // Derivative term.
         let derivative = match self.prev_error {
             Some(prev) => (error - prev) / dt,
             None => 0.0,
         };
+        let derivative = if self.derivative_tau > 0.0 {
+            self.filtered_derivative +=
+                (derivative - self.filtered_derivative) * dt / (self.derivative_tau + dt);
+            self.filtered_derivative
+        } else {
+            derivative
+        };
         let d = self.kd * derivative;
 
         self.prev_error = Some(error);
 
-        p + i + d
+        // Integral term.
+        let integral = self.integral + error * dt;
+        let raw = p + self.ki * integral + d;
+
+        let output = match self.limits {
+            Some((min, max)) => raw.clamp(min, max),
+            None => raw,
+        };
+
+        // Anti-windup: don't integrate while saturated in the direction the
+        // error is pushing.
+        let winding_up = output != raw && (raw - output).signum() == error.signum();
+        if !winding_up {
+            self.integral = integral;
+        }
+
+        self.history.push(output);
+
+        output
     }
 }
 
@@ -106,4 +199,97 @@ mod tests {
         let u = pid.update(2.0, 0.02);
         assert_eq!(u, 2.0); // purely proportional
     }
+
+    #[test]
+    fn alternating_error_is_flagged_oscillating() {
+        let mut pid = Pid::new(1.0, 0.0, 0.1);
+        for k in 0..2 * OUTPUT_HISTORY {
+            let error = if k % 2 == 0 { 1.0 } else { -1.0 };
+            pid.update(error, 0.016);
+        }
+        assert!(pid.is_oscillating());
+    }
+
+    #[test]
+    fn converging_error_is_not_oscillating() {
+        let mut pid = Pid::new(1.0, 0.0, 0.1);
+        for k in 0..2 * OUTPUT_HISTORY {
+            pid.update((-0.1 * k as f64).exp(), 0.016);
+        }
+        assert!(!pid.is_oscillating());
+    }
+
+    #[test]
+    fn clamped_output_does_not_wind_up() {
+        let mut pid = Pid::new(1.0, 1.0, 0.0).with_limits(-1.0, 1.0);
+        for _ in 0..100 {
+            let u = pid.update(10.0, 0.02);
+            assert_eq!(u, 1.0);
+        }
+        assert_eq!(pid.integral, 0.0);
+
+        // Reversing the error reverses the output straight away.
+        let u = pid.update(-0.5, 0.02);
+        assert!(u < 0.0);
+
+        pid.reset();
+        assert_eq!(pid.integral, 0.0);
+    }
+
+    #[test]
+    fn unclamped_output_is_unchanged() {
+        let mut limited = Pid::new(1.0, 0.5, 0.1).with_limits(-100.0, 100.0);
+        let mut unlimited = Pid::new(1.0, 0.5, 0.1);
+        for k in 0..10 {
+            let error = 0.1 * k as f64;
+            assert_eq!(limited.update(error, 0.02), unlimited.update(error, 0.02));
+        }
+    }
+
+    #[test]
+    fn filtered_derivative_smooths_step() {
+        let dt = 0.01;
+        let mut raw = Pid::new(0.0, 0.0, 1.0);
+        let mut filtered = Pid::new(0.0, 0.0, 1.0).with_derivative_filter(0.1);
+
+        let errors = [0.0, 1.0, 1.0, 1.0];
+        let raw_out: Vec<f64> = errors.iter().map(|&e| raw.update(e, dt)).collect();
+        let filtered_out: Vec<f64> = errors.iter().map(|&e| filtered.update(e, dt)).collect();
+
+        // Unfiltered: a single spike of 1/dt on the step.
+        assert!((raw_out[1] - 1.0 / dt).abs() < 1e-9);
+        assert_eq!(raw_out[2], 0.0);
+
+        // Filtered: a much smaller peak that decays over the following ticks.
+        assert!(filtered_out[1] < 0.2 * raw_out[1]);
+        assert!(filtered_out[2] > 0.0 && filtered_out[2] < filtered_out[1]);
+        assert!(filtered_out[3] > 0.0 && filtered_out[3] < filtered_out[2]);
+    }
+
+    #[test]
+    fn zero_tau_matches_unfiltered() {
+        let mut plain = Pid::new(1.0, 0.5, 0.2);
+        let mut zero_tau = Pid::new(1.0, 0.5, 0.2).with_derivative_filter(0.0);
+        for k in 0..10 {
+            let error = (k as f64).sin();
+            assert_eq!(plain.update(error, 0.02), zero_tau.update(error, 0.02));
+        }
+    }
+
+    #[test]
+    fn pi_output_is_tick_rate_independent() {
+        let output_after = |dt: f64, ticks: usize| {
+            let mut pid = Pid::new(2.0, 0.5, 0.0);
+            let mut output = 0.0;
+            for _ in 0..ticks {
+                output = pid.update(1.5, dt);
+            }
+            output
+        };
+
+        // One second of simulated time at 60 Hz and at 240 Hz.
+        let coarse = output_after(1.0 / 60.0, 60);
+        let fine = output_after(1.0 / 240.0, 240);
+        assert!((coarse - fine).abs() < 1e-9);
+    }
 }
@@ -1,25 +1,156 @@
+use crate::firing::{
+    firing_solution_const_accel, firing_solution_const_vel, intercept_times_const_accel,
+};
+pub use crate::firing::{firing_solution_ctr, turn_rate_from_velocities};
+use crate::{BULLET_SPEED, MAX_FLIGHT_TIME};
 use oort_api::prelude::*;
-use crate::BULLET_SPEED;
 
-const MAX_ITER: usize = 100;
+/// Time constant (s) of the low-pass on the finite-differenced acceleration.
+const ACCEL_SMOOTHING_TIME: f64 = 0.1;
 
+/// Default `Target::tie_window` (s).
+pub(crate) const INTERCEPT_TIE_WINDOW: f64 = 1.0;
+
+#[derive(Debug, Clone)]
 pub struct Target {
     pub position: Vec2,
     pub velocity: Vec2,
     pub acceleration: Vec2,
     pub time_to_intercept: Option<f64>,
     pub intercept_point: Option<Vec2>,
+    /// When set, the intercept aimed nearest this heading (rad) is chosen
+    /// over the earliest one, to save slewing during a burst.
+    pub preferred_heading: Option<f64>,
+    /// How much later (s) than the earliest an intercept may be and still win
+    /// on `preferred_heading`; later ones aren't worth the extra flight time.
+    pub tie_window: f64,
+    /// Intercepts later than this (s) are rejected: the bullet expires first.
+    /// Applies to every solution this target computes.
+    pub max_flight_time: f64,
+    /// Shooter position, velocity and projectile speed at the last
+    /// `update_firing_solution`.
+    observer: Vec2,
+    observer_velocity: Vec2,
+    bullet_speed: f64,
+    /// Velocity at the last `update_measured`, for differencing.
+    prev_velocity: Option<Vec2>,
+}
+
+/// A solved intercept: a bullet fired from `observer` now meets the target at
+/// `intercept_point` after `time_to_intercept` seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FiringSolution {
+    pub time_to_intercept: f64,
+    pub intercept_point: Vec2,
+    pub observer: Vec2,
+    pub observer_velocity: Vec2,
+    /// Target velocity at the intercept.
+    pub target_velocity: Vec2,
+    /// Distance (m) by which bullet and target fail to meet; zero for an exact root.
+    pub residual: f64,
+}
+
+/// Weights and normalizing scales for [`FiringSolution::quality_score`].
+const QUALITY_WEIGHT_RESIDUAL: f64 = 0.4;
+const QUALITY_WEIGHT_TIME: f64 = 0.4;
+const QUALITY_WEIGHT_CROSSING: f64 = 0.2;
+const QUALITY_RESIDUAL_SCALE: f64 = 5.0; // m
+const QUALITY_TIME_SCALE: f64 = 2.0; // s
+
+impl FiringSolution {
+    /// Muzzle velocity `u` (relative to the shooter) that carries a bullet to
+    /// the intercept point, i.e. `u = (intercept_point - observer) / t` with
+    /// the shooter's own velocity removed.
+    ///
+    /// For a valid solution `|u|` equals the bullet speed, so comparing the two
+    /// checks the solver's root.
+    pub fn bullet_velocity(&self) -> Vec2 {
+        (self.intercept_point - self.observer) / self.time_to_intercept - self.observer_velocity
+    }
+
+    /// Intercept point relative to where the observer will be at intercept.
+    fn relative_intercept(&self) -> Vec2 {
+        self.intercept_point - self.observer - self.observer_velocity * self.time_to_intercept
+    }
+
+    /// World-frame bearing (rad) from the observer to the intercept; this is
+    /// also the direction to point the gun.
+    pub fn bearing(&self) -> f64 {
+        self.relative_intercept().angle()
+    }
+
+    /// Distance (m) from the observer to the target at intercept.
+    pub fn range(&self) -> f64 {
+        self.relative_intercept().length()
+    }
+
+    /// Rate of change of `range` (m/s) at intercept; negative when closing.
+    pub fn range_rate(&self) -> f64 {
+        let v_rel = self.target_velocity - self.observer_velocity;
+        self.relative_intercept().normalize().dot(v_rel)
+    }
+
+    /// Aim point on the near edge of a target hull of radius `target_radius`:
+    /// the intercept point pulled toward the observer along the line of fire.
+    /// A point target (`target_radius = 0`) leaves it unchanged.
+    pub fn hull_aim_point(&self, target_radius: f64) -> Vec2 {
+        self.intercept_point - self.relative_intercept().normalize() * target_radius
+    }
+
+    /// Linear blend from `self` (`t = 0`) to `other` (`t = 1`), used to ease
+    /// the aim across a switch between prediction models.
+    pub fn lerp(&self, other: &FiringSolution, t: f64) -> FiringSolution {
+        let mix = |a: Vec2, b: Vec2| a + (b - a) * t;
+        FiringSolution {
+            time_to_intercept: self.time_to_intercept
+                + (other.time_to_intercept - self.time_to_intercept) * t,
+            intercept_point: mix(self.intercept_point, other.intercept_point),
+            observer: mix(self.observer, other.observer),
+            observer_velocity: mix(self.observer_velocity, other.observer_velocity),
+            target_velocity: mix(self.target_velocity, other.target_velocity),
+            residual: self.residual + (other.residual - self.residual) * t,
+        }
+    }
+
+    /// Scores the shot in `[0, 1]`, higher is better, as a weighted sum of:
+    /// - residual: `1 / (1 + residual / 5 m)`, weight 0.4;
+    /// - flight time: `1 / (1 + t / 2 s)`, weight 0.4;
+    /// - crossing angle: `(1 + cos θ) / 2`, weight 0.2, where `θ` is the angle
+    ///   between the bullet's heading and the target's approach (0 = head-on).
+    pub fn quality_score(&self) -> f64 {
+        let residual = 1.0 / (1.0 + self.residual / QUALITY_RESIDUAL_SCALE);
+        let time = 1.0 / (1.0 + self.time_to_intercept / QUALITY_TIME_SCALE);
+
+        let v_rel = self.target_velocity - self.observer_velocity;
+        let head_on = if v_rel.length() > 1e-6 {
+            -v_rel.normalize().dot(self.bullet_velocity().normalize())
+        } else {
+            1.0 // A stationary target is as easy as head-on.
+        };
+        let crossing = 0.5 * (1.0 + head_on);
+
+        QUALITY_WEIGHT_RESIDUAL * residual
+            + QUALITY_WEIGHT_TIME * time
+            + QUALITY_WEIGHT_CROSSING * crossing
+    }
 }
 
 impl Target {
     pub fn new(r: Vec2, v: Vec2, a: Vec2) -> Self {
-            Self {
-                position: r,
-                velocity: v,
-                acceleration: a,
-                time_to_intercept: None,
-                intercept_point: None,
-            }
+        Self {
+            position: r,
+            velocity: v,
+            acceleration: a,
+            time_to_intercept: None,
+            intercept_point: None,
+            preferred_heading: None,
+            tie_window: INTERCEPT_TIE_WINDOW,
+            max_flight_time: MAX_FLIGHT_TIME,
+            observer: vec2(0.0, 0.0),
+            observer_velocity: vec2(0.0, 0.0),
+            bullet_speed: BULLET_SPEED,
+            prev_velocity: None,
+        }
     }
 
     pub fn update_state(&mut self, r: Vec2, v: Vec2, a: Vec2) {
@@ -28,107 +159,572 @@ impl Target {
         self.acceleration = a;
     }
 
-    pub fn update_firing_solution(&mut self) {
-        let r_rel = self.position - position();
-        let v_rel = self.velocity - velocity();
-
-        let t_guess = match self.time_to_intercept {
-            Some(t) => t,
-            None => {
-                // Initialize with constant velocity solution
-                let maybe_guess = firing_solution_const_vel(r_rel, v_rel, BULLET_SPEED);
-                if let Some((t, _)) = maybe_guess {
-                    t
-                } else {
-                    // No solution found
-                    self.time_to_intercept = None;
-                    self.intercept_point = None;
-                    return;
-                }
+    /// Updates position and velocity from a measurement `dt` seconds after the
+    /// previous one and re-estimates acceleration from the change in velocity.
+    ///
+    /// The raw difference is low-pass filtered (time constant
+    /// `ACCEL_SMOOTHING_TIME`, discretized exactly) to tame measurement noise.
+    /// With no previous measurement the acceleration is taken as zero.
+    pub fn update_measured(&mut self, r: Vec2, v: Vec2, dt: f64) {
+        let a = match self.prev_velocity {
+            Some(prev) if dt > 0.0 => {
+                let raw = (v - prev) / dt;
+                let alpha = 1.0 - (-dt / ACCEL_SMOOTHING_TIME).exp();
+                self.acceleration + (raw - self.acceleration) * alpha
             }
+            Some(_) => self.acceleration,
+            None => vec2(0.0, 0.0),
+        };
+        self.prev_velocity = Some(v);
+        self.update_state(r, v, a);
+    }
+
+    /// A target seeded from a radar contact, with zero acceleration. The
+    /// contact's velocity is the baseline for the next `update_from_scan`.
+    pub fn from_scan(contact: &ScanResult) -> Self {
+        let mut target = Self::new(contact.position, contact.velocity, vec2(0.0, 0.0));
+        target.prev_velocity = Some(contact.velocity);
+        target
+    }
+
+    /// Updates from a radar contact `dt` seconds after the previous one,
+    /// re-estimating acceleration as in `update_measured`.
+    pub fn update_from_scan(&mut self, contact: &ScanResult, dt: f64) {
+        self.update_measured(contact.position, contact.velocity, dt);
+    }
+
+    /// The current firing solution, if `update_firing_solution` found one.
+    pub fn firing_solution(&self) -> Option<FiringSolution> {
+        let t = self.time_to_intercept?;
+        let intercept_point = self.intercept_point?;
+        let range = (intercept_point - self.observer - self.observer_velocity * t).length();
+        Some(FiringSolution {
+            time_to_intercept: t,
+            intercept_point,
+            observer: self.observer,
+            observer_velocity: self.observer_velocity,
+            target_velocity: self.velocity + self.acceleration * t,
+            residual: (range - self.bullet_speed * t).abs(),
+        })
+    }
+
+    /// Where to draw a HUD lead pip: the intercept point, if a solution exists.
+    pub fn lead_pip(&self) -> Option<Vec2> {
+        self.intercept_point
+    }
+
+    /// Nominal, left, and right aim points for a three-round bracket against a
+    /// target that may jink: the left/right aims assume it adds
+    /// `max_lateral_accel` perpendicular to its velocity for the whole flight.
+    ///
+    /// Requires a current firing solution.
+    pub fn bracket_aim_points(
+        &self,
+        max_lateral_accel: f64,
+        bullet_speed: f64,
+    ) -> Option<(Vec2, Vec2, Vec2)> {
+        self.time_to_intercept?;
+        let r_rel = self.position - self.observer;
+        let v_rel = self.velocity - self.observer_velocity;
+
+        // Left of the direction of travel, or of the line of sight if stationary.
+        let forward = if self.velocity.length() > 1e-6 {
+            self.velocity
+        } else {
+            r_rel
         };
-        let t = firing_solution_const_accel(r_rel, v_rel, self.acceleration, BULLET_SPEED, t_guess, 1e-4);
-        if t <= 0.0 {
+        let left = vec2(-forward.y, forward.x).normalize() * max_lateral_accel;
+
+        let aim = |a: Vec2| {
+            firing_solution_const_accel(r_rel, v_rel, a, bullet_speed, self.max_flight_time)
+                .map(|t| self.position + self.velocity * t + 0.5 * a * t * t)
+        };
+        Some((
+            aim(self.acceleration)?,
+            aim(self.acceleration + left)?,
+            aim(self.acceleration - left)?,
+        ))
+    }
+
+    /// Solves for a projectile fired at `bullet_speed` from this ship.
+    pub fn update_firing_solution(&mut self, bullet_speed: f64) {
+        self.update_firing_solution_from(position(), velocity(), bullet_speed);
+    }
+
+    /// Like `update_firing_solution`, but with bullets leaving from `observer`
+    /// (e.g. the muzzle rather than the ship's centroid) at `observer_velocity`.
+    pub fn update_firing_solution_from(
+        &mut self,
+        observer: Vec2,
+        observer_velocity: Vec2,
+        bullet_speed: f64,
+    ) {
+        self.observer = observer;
+        self.observer_velocity = observer_velocity;
+        self.bullet_speed = bullet_speed;
+
+        let Some(t) = self.intercept_time(self.acceleration) else {
             // No valid intercept time
             self.time_to_intercept = None;
             self.intercept_point = None;
             return;
-        }
+        };
         self.time_to_intercept = Some(t);
-        self.intercept_point = Some(self.position + self.velocity * t + 0.5 * self.acceleration * t * t);
+        self.intercept_point =
+            Some(self.position + self.velocity * t + 0.5 * self.acceleration * t * t);
+    }
+
+    pub fn update_firing_solution_blended(&mut self, bullet_speed: f64, maneuver_confidence: f64) {
+        self.update_firing_solution_blended_from(
+            position(),
+            velocity(),
+            bullet_speed,
+            maneuver_confidence,
+        );
+    }
+
+    /// Blends the constant-velocity (ballistic) and constant-acceleration
+    /// (maneuvering) intercepts by `maneuver_confidence` in `[0, 1]`: 0 gives
+    /// the CV solution, 1 the CA solution. If only one model has a solution,
+    /// it is used as-is.
+    pub fn update_firing_solution_blended_from(
+        &mut self,
+        observer: Vec2,
+        observer_velocity: Vec2,
+        bullet_speed: f64,
+        maneuver_confidence: f64,
+    ) {
+        self.observer = observer;
+        self.observer_velocity = observer_velocity;
+        self.bullet_speed = bullet_speed;
+
+        let solve = |a: Vec2| {
+            let t = self.intercept_time(a)?;
+            Some((t, self.position + self.velocity * t + 0.5 * a * t * t))
+        };
+        let cv = solve(vec2(0.0, 0.0));
+        let ca = solve(self.acceleration);
+
+        let w = maneuver_confidence.clamp(0.0, 1.0);
+        let blended = match (cv, ca) {
+            (Some((t_cv, p_cv)), Some((t_ca, p_ca))) => {
+                Some((t_cv + (t_ca - t_cv) * w, p_cv + (p_ca - p_cv) * w))
+            }
+            (cv, ca) => ca.or(cv),
+        };
+        self.time_to_intercept = blended.map(|(t, _)| t);
+        self.intercept_point = blended.map(|(_, p)| p);
+    }
+
+    /// Intercept time from the current observer assuming target acceleration `a`.
+    fn intercept_time(&self, a: Vec2) -> Option<f64> {
+        let r_rel = self.position - self.observer;
+        let v_rel = self.velocity - self.observer_velocity;
+
+        let maybe_t = match self.preferred_heading {
+            // Of the intercepts within `tie_window` of the earliest, take the
+            // one aimed nearest the heading.
+            Some(heading) => {
+                let off_heading = |t: f64| {
+                    angle_diff(heading, (r_rel + v_rel * t + 0.5 * a * t * t).angle()).abs()
+                };
+                let times = intercept_times_const_accel(
+                    r_rel,
+                    v_rel,
+                    a,
+                    self.bullet_speed,
+                    self.max_flight_time,
+                );
+                let latest = times.first()? + self.tie_window;
+                times
+                    .into_iter()
+                    .take_while(|&t| t <= latest)
+                    .min_by(|&t1, &t2| off_heading(t1).total_cmp(&off_heading(t2)))
+            }
+            // Without acceleration the quartic collapses to the closed-form quadratic.
+            None if a.length() < 1e-9 => {
+                firing_solution_const_vel(r_rel, v_rel, self.bullet_speed).map(|(t, _)| t)
+            }
+            None => firing_solution_const_accel(
+                r_rel,
+                v_rel,
+                a,
+                self.bullet_speed,
+                self.max_flight_time,
+            ),
+        };
+        maybe_t.filter(|&t| t <= self.max_flight_time)
     }
 }
 
-/// Intercept a target moving with constant acceleration, using
-/// a constant speed bullet in 2d. Position and velocity are
-/// relative to the shooter.
-// 
-/// r, v, a: initial position, velocity, acceleration of target
-/// u: bullet velocity, |u| = bullet_speed
-/// t: time to intercept
-///
-/// Governing equation:
-///    r + v t + 0.5 a t^2 = u t
-fn firing_solution_const_accel(r_rel: Vec2, v_rel: Vec2, a_rel: Vec2, bullet_speed: f64, t_guess: f64, tol: f64) -> f64 {
-    let p4 = 0.5 * a_rel.dot(a_rel);
-    let p3 = v_rel.dot(a_rel);
-    let p2 = v_rel.dot(v_rel) + r_rel.dot(a_rel) - bullet_speed * bullet_speed;
-    let p1 = 2.0 * r_rel.dot(v_rel);
-    let p0 = r_rel.dot(r_rel);
-
-    // Solve at^4 + bt^3 + ct^2 + dt + e = 0
-    // This could be solved analytically, but we likely have a good guess from the previous
-    // game tick, so finding the root with Newton's method should be faster.
-    let mut t = t_guess;
-    let mut t_next;
-    for _ in 0..MAX_ITER {
-        let f = (((p4 * t + p3) * t + p2) * t + p1) * t + p0;
-        let df = (4.0 * p4 * t + 3.0 * p3) * t * t + 2.0 * p2 * t + p1;
-        if df.abs() < 1e-6 {
-            break; // Avoid division by zero
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bullet_velocity_reaches_intercept_at_bullet_speed() {
+        let r = vec2(1000.0, 0.0);
+        let v = vec2(0.0, 100.0);
+        let (t, _) = firing_solution_const_vel(r, v, BULLET_SPEED).unwrap();
+        let solution = FiringSolution {
+            time_to_intercept: t,
+            intercept_point: r + v * t,
+            observer: vec2(0.0, 0.0),
+            observer_velocity: vec2(0.0, 0.0),
+            target_velocity: v,
+            residual: 0.0,
+        };
+
+        let u = solution.bullet_velocity();
+        assert!((u.length() - BULLET_SPEED).abs() < 1e-6);
+
+        let miss = solution.observer + u * t - solution.intercept_point;
+        assert!(miss.length() < 1e-6);
+    }
+
+    #[test]
+    fn lead_pip_matches_intercept_point() {
+        let mut target = Target::new(vec2(1000.0, 0.0), vec2(0.0, 100.0), vec2(0.0, 0.0));
+        assert_eq!(target.lead_pip(), None);
+
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        assert!(target.intercept_point.is_some());
+        assert_eq!(target.lead_pip(), target.intercept_point);
+
+        // A target outrunning the bullet has no solution and so no pip.
+        let mut target = Target::new(vec2(1000.0, 0.0), vec2(2000.0, 0.0), vec2(0.0, 0.0));
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        assert_eq!(target.lead_pip(), None);
+    }
+
+    #[test]
+    fn intercept_beyond_max_flight_time_is_rejected() {
+        let far = vec2(1.5 * BULLET_SPEED * MAX_FLIGHT_TIME, 0.0);
+        let mut target = Target::new(far, vec2(0.0, 0.0), vec2(0.0, 0.0));
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        assert_eq!(target.time_to_intercept, None);
+        assert_eq!(target.intercept_point, None);
+
+        // The only root is found but rejected before refinement.
+        let t = firing_solution_const_accel(
+            far,
+            vec2(0.0, 0.0),
+            vec2(0.0, 0.0),
+            BULLET_SPEED,
+            MAX_FLIGHT_TIME,
+        );
+        assert_eq!(t, None);
+
+        let near = vec2(0.5 * BULLET_SPEED * MAX_FLIGHT_TIME, 0.0);
+        let mut target = Target::new(near, vec2(0.0, 0.0), vec2(0.0, 0.0));
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        assert!(target.time_to_intercept.is_some());
+    }
+
+    #[test]
+    fn bracket_spread_grows_with_lateral_accel_and_range() {
+        let spread = |range: f64, max_lateral_accel: f64| {
+            let mut target = Target::new(vec2(range, 0.0), vec2(0.0, 200.0), vec2(0.0, 0.0));
+            target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+            let (nominal, left, right) = target
+                .bracket_aim_points(max_lateral_accel, BULLET_SPEED)
+                .unwrap();
+
+            // Left and right straddle the nominal aim.
+            assert!((left - nominal).dot(right - nominal) < 0.0);
+            (left - right).length()
+        };
+
+        assert!(spread(2000.0, 100.0) > spread(2000.0, 50.0));
+        assert!(spread(4000.0, 50.0) > spread(2000.0, 50.0));
+    }
+
+    #[test]
+    fn short_head_on_shot_outscores_long_grazing_one() {
+        let head_on = FiringSolution {
+            time_to_intercept: 1.0,
+            intercept_point: vec2(1000.0, 0.0),
+            observer: vec2(0.0, 0.0),
+            observer_velocity: vec2(0.0, 0.0),
+            target_velocity: vec2(-100.0, 0.0),
+            residual: 0.1,
+        };
+        let grazing = FiringSolution {
+            time_to_intercept: 5.0,
+            intercept_point: vec2(5000.0, 0.0),
+            observer: vec2(0.0, 0.0),
+            observer_velocity: vec2(0.0, 0.0),
+            target_velocity: vec2(0.0, 100.0),
+            residual: 20.0,
+        };
+
+        let score = head_on.quality_score();
+        assert!((0.0..=1.0).contains(&score));
+        assert!(score > grazing.quality_score());
+    }
+
+    #[test]
+    fn lerp_endpoints_and_midpoint() {
+        let a = FiringSolution {
+            time_to_intercept: 1.0,
+            intercept_point: vec2(1000.0, 0.0),
+            observer: vec2(0.0, 0.0),
+            observer_velocity: vec2(0.0, 0.0),
+            target_velocity: vec2(0.0, 100.0),
+            residual: 0.0,
+        };
+        let b = FiringSolution {
+            time_to_intercept: 2.0,
+            intercept_point: vec2(1000.0, 1000.0),
+            target_velocity: vec2(0.0, 200.0),
+            ..a
+        };
+
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+
+        let mid = a.lerp(&b, 0.5);
+        assert!((mid.time_to_intercept - 1.5).abs() < 1e-12);
+        assert!((mid.intercept_point - vec2(1000.0, 500.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn polar_form_of_head_on_intercept() {
+        // Ship at the origin facing +x; target dead ahead, closing head-on.
+        let heading = 0.0;
+        let mut target = Target::new(vec2(2000.0, 0.0), vec2(-100.0, 0.0), vec2(0.0, 0.0));
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        let solution = target.firing_solution().unwrap();
+
+        assert!(angle_diff(heading, solution.bearing()).abs() < 1e-9);
+        assert!((solution.range() - BULLET_SPEED * solution.time_to_intercept).abs() < 1e-3);
+        assert!((solution.range_rate() + 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn const_accel_no_solution() {
+        // Target outrunning the bullet and still accelerating away.
+        let mut target = Target::new(vec2(1000.0, 0.0), vec2(2000.0, 0.0), vec2(10.0, 0.0));
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        assert_eq!(target.time_to_intercept, None);
+        assert_eq!(target.intercept_point, None);
+    }
+
+    #[test]
+    fn hull_aim_point_is_near_edge() {
+        let mut target = Target::new(vec2(1000.0, 500.0), vec2(-50.0, 0.0), vec2(0.0, 0.0));
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        let solution = target.firing_solution().unwrap();
+        let centroid_range = solution.intercept_point.length();
+
+        let edge = solution.hull_aim_point(40.0);
+        assert!((centroid_range - edge.length() - 40.0).abs() < 1e-9);
+
+        assert_eq!(solution.hull_aim_point(0.0), solution.intercept_point);
+    }
+
+    #[test]
+    fn preferred_heading_picks_intercept_nearest_heading() {
+        // A fast target crossing just past us can be hit on the way in (ahead,
+        // t ~ 0.34 s) or on the way out (behind, t ~ 1.0 s).
+        let mut target = Target::new(
+            vec2(1000.0, 100.0),
+            vec2(-2.0 * BULLET_SPEED, 0.0),
+            vec2(0.0, 0.0),
+        );
+        let observer = vec2(0.0, 0.0);
+
+        target.update_firing_solution_from(observer, vec2(0.0, 0.0), BULLET_SPEED);
+        let earliest = target.time_to_intercept.unwrap();
+
+        target.preferred_heading = Some(0.0);
+        target.update_firing_solution_from(observer, vec2(0.0, 0.0), BULLET_SPEED);
+        assert!((target.time_to_intercept.unwrap() - earliest).abs() < 1e-9);
+        assert!(target.intercept_point.unwrap().x > 0.0);
+
+        target.preferred_heading = Some(std::f64::consts::PI);
+        target.update_firing_solution_from(observer, vec2(0.0, 0.0), BULLET_SPEED);
+        assert!(target.time_to_intercept.unwrap() > earliest + 0.5);
+        assert!(target.intercept_point.unwrap().x < 0.0);
+    }
+
+    #[test]
+    fn preferred_heading_does_not_pick_a_much_later_intercept() {
+        // Crossing at 1.2x bullet speed: met ahead at t ~ 0.46 s or behind at
+        // t ~ 5.0 s, far outside the tie window.
+        let mut target = Target::new(
+            vec2(1000.0, 100.0),
+            vec2(-1.2 * BULLET_SPEED, 0.0),
+            vec2(0.0, 0.0),
+        );
+        let observer = vec2(0.0, 0.0);
+        target.preferred_heading = Some(std::f64::consts::PI);
+        target.update_firing_solution_from(observer, vec2(0.0, 0.0), BULLET_SPEED);
+        assert!(target.time_to_intercept.unwrap() < 0.5);
+        assert!(target.intercept_point.unwrap().x > 0.0);
+
+        // A window wide enough to tie them lets the heading decide.
+        target.tie_window = 5.0;
+        target.update_firing_solution_from(observer, vec2(0.0, 0.0), BULLET_SPEED);
+        assert!(target.time_to_intercept.unwrap() > 4.5);
+        assert!(target.intercept_point.unwrap().x < 0.0);
+    }
+
+    #[test]
+    fn blended_solution_interpolates_cv_and_ca() {
+        let observer = vec2(0.0, 0.0);
+        let (r, v, a) = (vec2(2000.0, 0.0), vec2(0.0, 200.0), vec2(-50.0, 30.0));
+        let solve = |a: Vec2| {
+            let mut target = Target::new(r, v, a);
+            target.update_firing_solution_from(observer, vec2(0.0, 0.0), BULLET_SPEED);
+            (
+                target.time_to_intercept.unwrap(),
+                target.intercept_point.unwrap(),
+            )
+        };
+        let (t_cv, p_cv) = solve(vec2(0.0, 0.0));
+        let (t_ca, p_ca) = solve(a);
+        assert!((p_cv - p_ca).length() > 1.0);
+
+        let mut target = Target::new(r, v, a);
+        for (confidence, t_expected, p_expected) in [
+            (0.0, t_cv, p_cv),
+            (1.0, t_ca, p_ca),
+            (
+                0.25,
+                t_cv + 0.25 * (t_ca - t_cv),
+                p_cv + (p_ca - p_cv) * 0.25,
+            ),
+        ] {
+            target.update_firing_solution_blended_from(
+                observer,
+                vec2(0.0, 0.0),
+                BULLET_SPEED,
+                confidence,
+            );
+            assert!((target.time_to_intercept.unwrap() - t_expected).abs() < 1e-9);
+            assert!((target.intercept_point.unwrap() - p_expected).length() < 1e-9);
         }
-        t_next = t - f / df;
-        if (t_next - t).abs() < tol {
-            break; // Converged
+    }
+
+    #[test]
+    fn faster_weapon_intercepts_sooner() {
+        let (slow, fast) = (1000.0, 4000.0);
+        let mut target = Target::new(vec2(2000.0, 500.0), vec2(-50.0, 80.0), vec2(0.0, 0.0));
+
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), slow);
+        let slow_solution = target.firing_solution().unwrap();
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), fast);
+        let fast_solution = target.firing_solution().unwrap();
+
+        assert!(fast_solution.time_to_intercept < slow_solution.time_to_intercept);
+        assert!((slow_solution.range() - slow * slow_solution.time_to_intercept).abs() < 1e-3);
+        assert!((fast_solution.range() - fast * fast_solution.time_to_intercept).abs() < 1e-3);
+    }
+
+    #[test]
+    fn from_scan_seeds_state_from_contact() {
+        let contact = ScanResult {
+            class: Class::Fighter,
+            position: vec2(1200.0, -300.0),
+            velocity: vec2(-40.0, 25.0),
+            rssi: -30.0,
+            snr: 20.0,
+        };
+        let mut target = Target::from_scan(&contact);
+        assert_eq!(target.position, contact.position);
+        assert_eq!(target.velocity, contact.velocity);
+        assert_eq!(target.acceleration, vec2(0.0, 0.0));
+        assert_eq!(target.time_to_intercept, None);
+
+        // The next contact's change in velocity feeds the acceleration estimate.
+        let dt = 0.5;
+        let next = ScanResult {
+            position: vec2(1180.0, -287.5),
+            velocity: vec2(-40.0, 35.0),
+            ..contact
+        };
+        target.update_from_scan(&next, dt);
+        assert_eq!(target.position, next.position);
+        assert_eq!(target.velocity, next.velocity);
+        let alpha = 1.0 - (-dt / ACCEL_SMOOTHING_TIME).exp();
+        assert!((target.acceleration - vec2(0.0, 20.0 * alpha)).length() < 1e-9);
+    }
+
+    #[test]
+    fn measured_acceleration_converges() {
+        let a = vec2(3.0, -8.0);
+        let (r0, v0) = (vec2(2000.0, 500.0), vec2(-100.0, 40.0));
+        let dt = 1.0 / 60.0;
+        let mut target = Target::new(r0, v0, vec2(0.0, 0.0));
+
+        target.update_measured(r0, v0, dt);
+        assert_eq!(target.acceleration, vec2(0.0, 0.0));
+
+        let mut prev_error = a.length();
+        for i in 1..=120 {
+            let t = i as f64 * dt;
+            target.update_measured(r0 + v0 * t + 0.5 * a * t * t, v0 + a * t, dt);
+            let error = (target.acceleration - a).length();
+            assert!(error < prev_error);
+            prev_error = error;
         }
-        t = t_next;
+        assert!(prev_error < 1e-3);
     }
-    t
-}
 
-/// Computes an intercept firing solution assuming constant velocities for both
-/// the ship and the target.
-///
-/// The calculation solves the classic pursuit problem in 2-D by determining
-/// the earliest positive time `t` at which a bullet—shot today at constant
-/// speed `bullet_speed`—can meet the target.  If no positive‐time solution
-/// exists (i.e. the discriminant is negative or both roots are non-positive),
-/// `None` is returned.
-///
-/// Returns the time‐to‐impact `t` (seconds) together with the **relative** aim
-/// point, expressed in the ship-centred coordinate frame (`r_rel + v_rel·t`).
-fn firing_solution_const_vel(r_rel: Vec2, v_rel: Vec2, bullet_speed: f64) -> Option<(f64, Vec2)> {
-    // Quadratic coefficients for |r_rel + v_rel·t| = bullet_speed·t.
-    let a = v_rel.dot(v_rel) - bullet_speed * bullet_speed;
-    let b = 2.0 * v_rel.dot(r_rel);
-    let c = r_rel.dot(r_rel);
-
-    // Discriminant of the quadratic.
-    let disc = b * b - 4.0 * a * c;
-    if disc < 0.0 {
-        return None;
-    }
-
-    let sqrt_disc = disc.sqrt();
-    let (t1, t2) = ((-b - sqrt_disc) / (2.0 * a), (-b + sqrt_disc) / (2.0 * a));
-
-    // Earliest positive interception time.
-    let t = match (t1 > 0.0, t2 > 0.0) {
-        (true, true) => t1.min(t2),
-        (true, false) => t1,
-        (false, true) => t2,
-        _ => return None,
-    };
-
-    Some((t, r_rel + v_rel * t))
+    #[test]
+    fn intercept_beyond_bullet_lifetime_is_rejected() {
+        let lifetime = 2.0;
+        let reach = BULLET_SPEED * lifetime;
+
+        let mut inside = Target::new(vec2(reach - 10.0, 0.0), vec2(0.0, 0.0), vec2(0.0, 0.0));
+        inside.max_flight_time = lifetime;
+        inside.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        assert!(inside.time_to_intercept.unwrap() < lifetime);
+
+        let mut outside = Target::new(vec2(reach + 10.0, 0.0), vec2(0.0, 0.0), vec2(0.0, 0.0));
+        outside.max_flight_time = lifetime;
+        outside.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        assert_eq!(outside.time_to_intercept, None);
+        assert_eq!(outside.intercept_point, None);
+        assert!(outside.firing_solution().is_none());
+        assert!(outside.bracket_aim_points(10.0, BULLET_SPEED).is_none());
+        assert!(inside.bracket_aim_points(10.0, BULLET_SPEED).is_some());
+
+        let (v, turn_rate) = (vec2(0.0, 0.0), 0.1);
+        let ctr =
+            |r: Vec2| firing_solution_ctr(r, v, turn_rate, BULLET_SPEED, vec2(0.0, 0.0), lifetime);
+        assert!(ctr(vec2(reach - 10.0, 0.0)).is_some());
+        assert!(ctr(vec2(reach + 10.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn lifetime_beyond_default_reaches_farther() {
+        // 15 s out: past the default lifetime, within a 20 s one.
+        let far = vec2(15.0 * BULLET_SPEED, 0.0);
+        let mut target = Target::new(far, vec2(0.0, 0.0), vec2(0.0, 0.0));
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        assert_eq!(target.time_to_intercept, None);
+
+        target.max_flight_time = 20.0;
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        assert!((target.time_to_intercept.unwrap() - 15.0).abs() < 1e-6);
+        target.preferred_heading = Some(0.0);
+        target.update_firing_solution_from(vec2(0.0, 0.0), vec2(0.0, 0.0), BULLET_SPEED);
+        assert!((target.time_to_intercept.unwrap() - 15.0).abs() < 1e-6);
+        assert!(target.bracket_aim_points(10.0, BULLET_SPEED).is_some());
+
+        let ctr = |lifetime| {
+            firing_solution_ctr(
+                far,
+                vec2(0.0, 0.0),
+                0.1,
+                BULLET_SPEED,
+                vec2(0.0, 0.0),
+                lifetime,
+            )
+        };
+        assert!(ctr(MAX_FLIGHT_TIME).is_none());
+        assert!(ctr(20.0).is_some());
+    }
 }
@@ -0,0 +1,107 @@
+/// Fixed-capacity ring buffer holding the `N` most recent values.
+///
+/// Storage is an inline array, so the buffer never allocates and is `Copy`
+/// whenever `T` is. Pushing onto a full buffer overwrites the oldest value.
+///
+/// # Example
+/// ```rust
+/// use naominagata::util::RingBuffer;
+///
+/// let mut history: RingBuffer<f64, 3> = RingBuffer::new(0.0);
+/// for x in [1.0, 2.0, 3.0, 4.0] {
+///     history.push(x);
+/// }
+/// assert_eq!(history.iter().collect::<Vec<_>>(), vec![2.0, 3.0, 4.0]);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RingBuffer<T: Copy, const N: usize> {
+    items: [T; N],
+    /// Index the next value is written to.
+    head: usize,
+    /// Number of valid values, at most `N`.
+    len: usize,
+}
+
+impl<T: Copy, const N: usize> RingBuffer<T, N> {
+    /// Creates an empty buffer. `fill` only initializes the backing array and
+    /// is never returned.
+    #[must_use]
+    pub const fn new(fill: T) -> Self {
+        Self {
+            items: [fill; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Appends `value`, evicting the oldest value if the buffer is full.
+    pub fn push(&mut self, value: T) {
+        self.items[self.head] = value;
+        self.head = (self.head + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// Number of values currently held.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub const fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Removes all values.
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Iterates over the held values from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        let start = (self.head + N - self.len) % N;
+        (0..self.len).map(move |k| self.items[(start + k) % N])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iterates_oldest_to_newest() {
+        let mut buf: RingBuffer<i32, 4> = RingBuffer::new(0);
+        buf.push(1);
+        buf.push(2);
+        buf.push(3);
+        assert_eq!(buf.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn wraps_around_and_evicts_oldest() {
+        let mut buf: RingBuffer<i32, 3> = RingBuffer::new(0);
+        for x in 1..=7 {
+            buf.push(x);
+        }
+        assert_eq!(buf.iter().collect::<Vec<_>>(), vec![5, 6, 7]);
+    }
+
+    #[test]
+    fn never_exceeds_capacity() {
+        let mut buf: RingBuffer<f64, 5> = RingBuffer::new(0.0);
+        assert!(buf.is_empty());
+        for k in 0..20 {
+            buf.push(k as f64);
+            assert!(buf.len() <= 5);
+        }
+        assert!(buf.is_full());
+        assert_eq!(buf.iter().count(), 5);
+
+        buf.clear();
+        assert!(buf.is_empty());
+        assert_eq!(buf.iter().count(), 0);
+    }
+}